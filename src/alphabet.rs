@@ -1,4 +1,6 @@
 use std::char;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 
 pub const NUMBER_LETTERS_IN_ALPHABET: usize = 26;
 
@@ -9,33 +11,249 @@ pub const ALPHABET: [char; NUMBER_LETTERS_IN_ALPHABET] = [
     'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
 ];
 
-///
+const LETTERS: [&str; NUMBER_LETTERS_IN_ALPHABET] = [
+    "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S",
+    "T", "U", "V", "W", "X", "Y", "Z",
+];
+
+/// Latin letters with diacritics, ligatures and the letters they are written as, generated from
+/// the Unicode character names of Latin-1 Supplement, Latin Extended-A to D, Latin Extended
+/// Additional and the Latin ligatures. A letter "with" any marks becomes its base letter, so
+/// umlauts become their base letter here, the German `AE`, `OE` and `UE` are up to the
+/// preprocessing profile.
+const DECOMPOSITIONS: [(&str, &str); 45] = [
+    ("ÀÁÂÃÄÅàáâãäåĀāĂăĄąǍǎǞǟǠǡǺǻȀȁȂȃȦȧȺḀḁẚ", "A"),
+    ("ẠạẢảẤấẦầẨẩẪẫẬậẮắẰằẲẳẴẵẶặⱥ", "A"),
+    ("ƀƁƂƃɃḂḃḄḅḆḇꞖꞗ", "B"),
+    ("ÇçĆćĈĉĊċČčƇƈȻȼḈḉꞒꞓꞔꟄ", "C"),
+    ("ÐðĎďĐđƊƋƌȡḊḋḌḍḎḏḐḑḒḓꟇꟈ", "D"),
+    ("ÈÉÊËèéêëĒēĔĕĖėĘęĚěȄȅȆȇȨȩɆɇḔḕḖḗḘḙḚḛḜḝ", "E"),
+    ("ẸẹẺẻẼẽẾếỀềỂểỄễỆệⱸ", "E"),
+    ("ƑƒḞḟꞘꞙ", "F"),
+    ("ĜĝĞğĠġĢģƓǤǥǦǧǴǵḠḡꞠꞡ", "G"),
+    ("ĤĥĦħȞȟḢḣḤḥḦḧḨḩḪḫẖⱧⱨꞕꞪ", "H"),
+    ("ÌÍÎÏìíîïĨĩĪīĬĭĮįİıƗǏǐȈȉȊȋḬḭḮḯỈỉỊị", "I"),
+    ("ĴĵǰȷɈɉꞲ", "J"),
+    ("ĶķƘƙǨǩḰḱḲḳḴḵⱩⱪꝀꝁꝂꝃꝄꝅꞢꞣ", "K"),
+    ("ĹĺĻļĽľĿŀŁłƚȴȽḶḷḸḹḺḻḼḽⱠⱡⱢꝈꝉꞎꞭ", "L"),
+    ("ḾḿṀṁṂṃⱮ", "M"),
+    ("ÑñŃńŅņŇňƝƞǸǹȠȵṄṅṆṇṈṉṊṋꞐꞑꞤꞥ", "N"),
+    ("ÒÓÔÕÖØòóôõöøŌōŎŏŐőƟƠơǑǒǪǫǬǭǾǿȌȍȎȏȪȫȬ", "O"),
+    ("ȭȮȯȰȱṌṍṎṏṐṑṒṓỌọỎỏỐốỒồỔổỖỗỘộỚớỜờỞởỠỡỢ", "O"),
+    ("ợⱺꝊꝋꝌꝍ", "O"),
+    ("ƤƥṔṕṖṗⱣꝐꝑꝒꝓꝔꝕ", "P"),
+    ("ɋꝖꝗꝘꝙ", "Q"),
+    ("ŔŕŖŗŘřȐȑȒȓɌɍṘṙṚṛṜṝṞṟⱤꞦꞧ", "R"),
+    ("ŚśŜŝŞşŠšſȘșȿṠṡṢṣṤṥṦṧṨṩẛẜẝⱾꞨꞩꟅꟉꟊ", "S"),
+    ("ŢţŤťŦŧƫƬƭƮȚțȶȾṪṫṬṭṮṯṰṱẗⱦ", "T"),
+    ("ÙÚÛÜùúûüŨũŪūŬŭŮůŰűŲųƯưǓǔǕǖǗǘǙǚǛǜȔȕȖȗ", "U"),
+    ("ṲṳṴṵṶṷṸṹṺṻỤụỦủỨứỪừỬửỮữỰựꞸꞹ", "U"),
+    ("ƲṼṽṾṿⱱⱴꝞꝟ", "V"),
+    ("ŴŵẀẁẂẃẄẅẆẇẈẉẘⱲⱳ", "W"),
+    ("ẊẋẌẍ", "X"),
+    ("ÝýÿŶŷŸƳƴȲȳɎɏẎẏẙỲỳỴỵỶỷỸỹỾỿ", "Y"),
+    ("ŹźŻżŽžƵƶȤȥɀẐẑẒẓẔẕⱫⱬⱿꟆ", "Z"),
+    ("ÆæǢǣǼǽ", "AE"),
+    ("ǄǅǆǱǲǳ", "DZ"),
+    ("ﬀ", "FF"),
+    ("ﬃ", "FFI"),
+    ("ﬄ", "FFL"),
+    ("ﬁ", "FI"),
+    ("ﬂ", "FL"),
+    ("Ĳĳ", "IJ"),
+    ("Ǉǈǉ", "LJ"),
+    ("Ǌǋǌ", "NJ"),
+    ("Œœ", "OE"),
+    ("ßẞ", "SS"),
+    ("ﬅﬆ", "ST"),
+    ("ÞþꝤꝥꝦꝧ", "TH"),
+];
+
+/// Characters that no letters stand for, with their positions in the text.
+#[derive(Debug, PartialEq, Eq)]
+pub struct InvalidCharacters {
+    pub characters: Vec<(usize, char)>,
+}
+
+/// The position of the letter `input` is written as, counting from 0 for `A`.
 ///
 /// # Arguments
 ///
-/// * `input`: any upper case character between A and Z
+/// * `input`: a Latin letter in either case, with or without diacritics, see [`normalise`]
 ///
 /// returns: usize
 ///
+/// Panics if `input` is not a letter or stands for several letters, like `ß` or `Æ`.
+///
 /// # Examples
 ///
 /// ```
 /// assert_eq!(get_position_in_alphabet('A'), 0);
-/// assert_eq!(get_position_in_alphabet('B'), 1);
-/// assert_eq!(get_position_in_alphabet('Z'), 25);
+/// assert_eq!(get_position_in_alphabet('b'), 1);
+/// assert_eq!(get_position_in_alphabet('Ž'), 25);
 ///
 /// ```
 pub fn get_position_in_alphabet(input: char) -> usize {
-    let upper = input.to_uppercase().next().unwrap() as usize;
-    let position_in_alphabet = upper - ASCII_LETTER_A;
-    assert!(position_in_alphabet < NUMBER_LETTERS_IN_ALPHABET);
-    position_in_alphabet
+    let letters = normalise(input).unwrap_or_else(|| panic!("{:?} is not a letter", input));
+    assert_eq!(letters.len(), 1, "{:?} stands for several letters", input);
+    letters.as_bytes()[0] as usize - ASCII_LETTER_A
+}
+
+/// The capital letters `input` is written as, e.g. `E` for `é` and `SS` for `ß`, or `None` if it
+/// is not a Latin letter.
+pub fn normalise(input: char) -> Option<&'static str> {
+    if input.is_ascii_alphabetic() {
+        return Some(LETTERS[to_index(input.to_ascii_uppercase()) as usize]);
+    }
+    DECOMPOSITIONS
+        .iter()
+        .find(|(characters, _)| characters.contains(input))
+        .map(|(_, letters)| *letters)
+}
+
+/// Whether `input` is a combining diacritical mark, which decomposed text writes after the
+/// letter it belongs to, e.g. `e\u{301}` for `é`.
+pub fn is_combining_mark(input: char) -> bool {
+    matches!(
+        input,
+        '\u{300}'..='\u{36F}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+/// Replaces every character of `text` by the letters it is written as, see [`normalise`].
+/// Combining marks after a letter are dropped.
+pub fn normalise_text(text: &str) -> Result<String, InvalidCharacters> {
+    let mut result = String::new();
+    let mut characters = Vec::new();
+    let mut after_letter = false;
+    for (position, c) in text.chars().enumerate() {
+        if after_letter && is_combining_mark(c) {
+            continue;
+        }
+        let letters = normalise(c);
+        match letters {
+            Some(letters) => result.push_str(letters),
+            None => characters.push((position, c)),
+        }
+        after_letter = letters.is_some();
+    }
+    if characters.is_empty() {
+        Ok(result)
+    } else {
+        Err(InvalidCharacters { characters })
+    }
+}
+
+/// Fast variant of [`get_position_in_alphabet`] for the hot path, `input` must be a capital
+/// letter.
+pub fn to_index(input: char) -> u8 {
+    debug_assert!(is_capital_letter(input));
+    input as u8 - ASCII_LETTER_A as u8
 }
 
-pub fn is_small_letter(input: char) -> bool {
-    ('a'..='z').contains(&input)
+pub fn from_index(index: u8) -> char {
+    ALPHABET[index as usize]
 }
 
 pub fn is_capital_letter(input: char) -> bool {
-    ('A'..='Z').contains(&input)
+    input.is_ascii_uppercase()
+}
+
+pub fn letters_from_positions(positions: &[usize]) -> String {
+    positions.iter().map(|p| ALPHABET[*p]).collect()
+}
+
+impl Display for InvalidCharacters {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let characters: Vec<String> = self
+            .characters
+            .iter()
+            .map(|(position, c)| format!("{:?} at {}", c, position))
+            .collect();
+        f.write_fmt(format_args!(
+            "no letters stand for {}",
+            characters.join(", ")
+        ))
+    }
+}
+
+impl Error for InvalidCharacters {}
+
+#[cfg(test)]
+mod tests {
+    use crate::alphabet::*;
+
+    #[test]
+    fn position_in_alphabet() {
+        assert_eq!(get_position_in_alphabet('A'), 0);
+        assert_eq!(get_position_in_alphabet('z'), 25);
+        assert_eq!(get_position_in_alphabet('é'), 4);
+    }
+
+    #[test]
+    #[should_panic]
+    fn position_of_non_letter() {
+        get_position_in_alphabet('1');
+    }
+
+    #[test]
+    #[should_panic]
+    fn position_of_sharp_s() {
+        get_position_in_alphabet('ß');
+    }
+
+    #[test]
+    fn normalise_letters() {
+        assert_eq!(normalise('q'), Some("Q"));
+        assert_eq!(normalise('Å'), Some("A"));
+        assert_eq!(normalise('ł'), Some("L"));
+        assert_eq!(normalise('ß'), Some("SS"));
+        assert_eq!(normalise('Œ'), Some("OE"));
+        assert_eq!(normalise('ﬁ'), Some("FI"));
+        assert_eq!(normalise('1'), None);
+        assert_eq!(normalise('Ж'), None);
+    }
+
+    #[test]
+    fn decompositions_are_unique() {
+        let characters: Vec<char> = DECOMPOSITIONS
+            .iter()
+            .flat_map(|(characters, _)| characters.chars())
+            .collect();
+        for c in &characters {
+            assert_eq!(characters.iter().filter(|other| *other == c).count(), 1);
+        }
+    }
+
+    #[test]
+    fn normalise_whole_text() {
+        assert_eq!(normalise_text("Fußgänger"), Ok("FUSSGANGER".to_string()));
+        let error = normalise_text("AB C1D").unwrap_err();
+        assert_eq!(error.characters, vec![(2, ' '), (4, '1')]);
+        assert_eq!(error.to_string(), "no letters stand for ' ' at 2, '1' at 4");
+    }
+
+    #[test]
+    fn normalise_latin_extended_letters() {
+        assert_eq!(normalise_text("ǍǎǐǒǔȘșȚț"), Ok("AAIOUSSTT".to_string()));
+        assert_eq!(normalise_text("ẞḂỳǼ"), Ok("SSBYAE".to_string()));
+    }
+
+    #[test]
+    fn combining_marks_after_letters_are_dropped() {
+        assert_eq!(normalise_text("Cafe\u{301}"), Ok("CAFE".to_string()));
+        assert_eq!(
+            normalise_text("s\u{326}a\u{308}\u{301}"),
+            Ok("SA".to_string())
+        );
+        let error = normalise_text("\u{301}A 1\u{301}").unwrap_err();
+        assert_eq!(
+            error.characters,
+            vec![(0, '\u{301}'), (2, ' '), (3, '1'), (4, '\u{301}')]
+        );
+    }
 }
@@ -0,0 +1,336 @@
+use crate::alphabet::NUMBER_LETTERS_IN_ALPHABET;
+use crate::message::Message;
+use crate::rotor::Rotor;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::iter::zip;
+use std::path::Path;
+
+/// Probability that two letters of German plaintext at the same position are equal.
+const PLAINTEXT_COINCIDENCE: f64 = 0.0762;
+const RANDOM_COINCIDENCE: f64 = 1.0 / NUMBER_LETTERS_IN_ALPHABET as f64;
+
+/// Two messages overlaid such that the key of `second` is `offset` letters ahead of the key of
+/// `first`, i.e. letter `i` of `second` is compared to letter `i + offset` of `first`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Alignment {
+    pub first: usize,
+    pub second: usize,
+    pub offset: usize,
+    pub overlap: usize,
+    pub repeats: usize,
+    pub decibans: f64,
+}
+
+/// Distance between the fast rotor letters of two message keys, inferred from a depth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyDistance {
+    pub first: usize,
+    pub second: usize,
+    pub distance: usize,
+}
+
+/// Fast rotor letters of message keys relative to the first message of the chain.
+pub struct Chain {
+    pub relative_keys: HashMap<usize, usize>,
+    pub conflicts: usize,
+}
+
+/// A rotor that can be in the fast position, together with the fast rotor letters of the first
+/// message of the chain that are consistent with its turnover.
+pub struct RightRotorCandidate {
+    pub rotor_id: &'static str,
+    pub keys: Vec<usize>,
+}
+
+impl Display for Alignment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{:>3} {:>3}  offset {:>3}  repeats {:>3}/{:<3}  {:>6.1} dB",
+            self.first, self.second, self.offset, self.repeats, self.overlap, self.decibans
+        ))
+    }
+}
+
+/// Turing's weight of evidence that two texts are in depth, in decibans.
+pub fn weight_of_evidence(overlap: usize, repeats: usize) -> f64 {
+    let repeat = 10.0 * (PLAINTEXT_COINCIDENCE / RANDOM_COINCIDENCE).log10();
+    let no_repeat = 10.0 * ((1.0 - PLAINTEXT_COINCIDENCE) / (1.0 - RANDOM_COINCIDENCE)).log10();
+    repeats as f64 * repeat + (overlap - repeats) as f64 * no_repeat
+}
+
+pub fn align(first: &str, second: &str, offset: usize) -> (usize, usize) {
+    let first = first.as_bytes().iter().skip(offset);
+    let mut overlap = 0;
+    let mut repeats = 0;
+    for (a, b) in zip(first, second.as_bytes()) {
+        overlap += 1;
+        if a == b {
+            repeats += 1;
+        }
+    }
+    (overlap, repeats)
+}
+
+/// The best scoring alignment for each pair of messages that overlap by at least
+/// `minimum_overlap` letters, strongest first.
+pub fn best_alignments(messages: &[Message], minimum_overlap: usize) -> Vec<Alignment> {
+    let mut result = Vec::new();
+    for first in 0..messages.len() {
+        for second in first + 1..messages.len() {
+            let candidates = alignments(messages, first, second, minimum_overlap)
+                .chain(alignments(messages, second, first, minimum_overlap));
+            let best = candidates.max_by(|a, b| a.decibans.total_cmp(&b.decibans));
+            result.extend(best);
+        }
+    }
+    result.sort_by(|a, b| b.decibans.total_cmp(&a.decibans));
+    result
+}
+
+fn alignments(
+    messages: &[Message],
+    first: usize,
+    second: usize,
+    minimum_overlap: usize,
+) -> impl Iterator<Item = Alignment> + '_ {
+    let first_text = &messages[first].text;
+    let second_text = &messages[second].text;
+    // offset zero is the same for both directions
+    let start = usize::from(first > second);
+    (start..first_text.len())
+        .map(move |offset| {
+            let (overlap, repeats) = align(first_text, second_text, offset);
+            Alignment {
+                first,
+                second,
+                offset,
+                overlap,
+                repeats,
+                decibans: weight_of_evidence(overlap, repeats),
+            }
+        })
+        .filter(move |alignment| alignment.overlap >= minimum_overlap)
+}
+
+/// Infers the distance of the fast rotor letters from alignments of at least `threshold`
+/// decibans. Since all indicators of a day are enciphered at the same Grundstellung, equal
+/// indicator letters mean equal key letters. Only pairs whose keys agree in the middle and slow
+/// rotor are used, because only then the offset is the distance of the fast rotor letters.
+pub fn key_distances(
+    messages: &[Message],
+    alignments: &[Alignment],
+    threshold: f64,
+) -> Vec<KeyDistance> {
+    alignments
+        .iter()
+        .filter(|alignment| alignment.decibans >= threshold)
+        .filter(|alignment| alignment.offset < NUMBER_LETTERS_IN_ALPHABET)
+        .filter(|alignment| {
+            let first = &messages[alignment.first].indicator;
+            let second = &messages[alignment.second].indicator;
+            first.get_first_triplet()[1..] == second.get_first_triplet()[1..]
+        })
+        .map(|alignment| KeyDistance {
+            first: alignment.first,
+            second: alignment.second,
+            distance: alignment.offset,
+        })
+        .collect()
+}
+
+/// Links message keys by their distances and by equal enciphered fast rotor letters, and
+/// returns the largest group of keys whose letters are known relative to each other.
+pub fn longest_chain(messages: &[Message], distances: &[KeyDistance]) -> Chain {
+    let mut edges: HashMap<usize, Vec<(usize, isize)>> = HashMap::new();
+    let mut add_edge = |first: usize, second: usize, distance: isize| {
+        edges.entry(first).or_default().push((second, distance));
+        edges.entry(second).or_default().push((first, -distance));
+    };
+    for distance in distances {
+        add_edge(distance.first, distance.second, distance.distance as isize);
+    }
+    for first in 0..messages.len() {
+        for second in first + 1..messages.len() {
+            if fast_letter(&messages[first]) == fast_letter(&messages[second]) {
+                add_edge(first, second, 0);
+            }
+        }
+    }
+
+    let mut best = Chain {
+        relative_keys: HashMap::new(),
+        conflicts: 0,
+    };
+    let mut visited = vec![false; messages.len()];
+    for root in 0..messages.len() {
+        if visited[root] {
+            continue;
+        }
+        let chain = chain_from(root, &edges, &mut visited);
+        if chain.relative_keys.len() > best.relative_keys.len() {
+            best = chain;
+        }
+    }
+    best
+}
+
+fn fast_letter(message: &Message) -> char {
+    message
+        .indicator
+        .get_first_triplet()
+        .chars()
+        .next()
+        .unwrap()
+}
+
+fn chain_from(
+    root: usize,
+    edges: &HashMap<usize, Vec<(usize, isize)>>,
+    visited: &mut [bool],
+) -> Chain {
+    let n = NUMBER_LETTERS_IN_ALPHABET as isize;
+    let mut relative_keys = HashMap::from([(root, 0)]);
+    let mut conflicts = 0;
+    let mut queue = VecDeque::from([root]);
+    visited[root] = true;
+    while let Some(current) = queue.pop_front() {
+        let current_key = relative_keys[&current] as isize;
+        for (next, distance) in edges.get(&current).into_iter().flatten() {
+            let key = (current_key + distance).rem_euclid(n) as usize;
+            match relative_keys.get(next) {
+                None => {
+                    relative_keys.insert(*next, key);
+                    visited[*next] = true;
+                    queue.push_back(*next);
+                }
+                Some(existing) if *existing == key => {}
+                Some(_) => conflicts += 1,
+            }
+        }
+    }
+    Chain {
+        relative_keys,
+        conflicts: conflicts / 2,
+    }
+}
+
+/// A depth between two keys with equal middle rotor letters can only exist if the fast rotor
+/// does not reach its turnover on the way from the first key to the second, as otherwise the
+/// middle rotors would differ. This rules out rotors for each placement of the chain.
+pub fn right_rotor_candidates(
+    path: &Path,
+    chain: &Chain,
+    distances: &[KeyDistance],
+    rotor_ids: &[&'static str],
+) -> Vec<RightRotorCandidate> {
+    let n = NUMBER_LETTERS_IN_ALPHABET;
+    let in_chain: Vec<&KeyDistance> = distances
+        .iter()
+        .filter(|distance| chain.relative_keys.contains_key(&distance.first))
+        .collect();
+    rotor_ids
+        .iter()
+        .map(|rotor_id| {
+            let rotor = Rotor::from_file(path, rotor_id);
+            let keys = (0..n)
+                .filter(|shift| {
+                    in_chain.iter().all(|distance| {
+                        let key = (chain.relative_keys[&distance.first] + shift) % n;
+                        rotor
+                            .get_turnover_positions()
+                            .iter()
+                            .all(|turnover| (turnover + 2 * n - key - 1) % n >= distance.distance)
+                    })
+                })
+                .collect();
+            RightRotorCandidate { rotor_id, keys }
+        })
+        .filter(|candidate| !candidate.keys.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::banburismus::*;
+    use crate::message::{preprocess_for_enigma, Indicator};
+    use crate::rotor::PATH;
+    use crate::rotorassembly::{RotorAssembly, CYPHER_ROTOR_IDS};
+    use crate::{plugboard, Enigma, Plugboard};
+
+    const GRUNDSTELLUNG: [usize; 3] = [9, 2, 20];
+    const KEYS: [&str; 4] = ["CMQ", "FMQ", "JMQ", "NMQ"];
+    const TEXTS: [&str; 4] = [
+        "An Oberkommando der Wehrmacht. Wetterbericht fuer den Abschnitt Nord. Wind aus \
+        Nordwest, Staerke fuenf, zunehmend. Sicht gering, tiefe Bewoelkung. Gegen Abend \
+        Regenschauer, in der Nacht Nebel in den Niederungen. Temperatur um null Grad. Keine \
+        besonderen Ereignisse an der Front. Ende der Meldung.",
+        "Die Division meldet: Feindliche Aufklaerung im Raum suedlich der Stadt wurde \
+        abgewiesen. Eigene Verluste gering, der Nachschub an Munition und Verpflegung ist \
+        gesichert. Die Stellungen werden in der kommenden Nacht weiter ausgebaut. Erbitten \
+        weitere Weisungen fuer den morgigen Tag. Der Kommandeur.",
+        "Befehl fuer den naechsten Tag: Die Abteilung sammelt sich bis zehn Uhr am \
+        Waldrand westlich des Dorfes und marschiert dann auf der Strasse nach Osten. \
+        Fahrzeuge sind mit voller Betriebsstoffausstattung bereitzuhalten. Meldungen ueber \
+        das Eintreffen sind sofort an den Stab zu geben. Gezeichnet der Chef.",
+        "Lagebericht des Regiments: Am Vormittag leichter Artilleriebeschuss auf die \
+        vorderen Linien, am Nachmittag ruhig. Die Verbindung zu den Nachbarn ist hergestellt. \
+        Ein Spaehtrupp hat die Bruecke erkundet, sie ist unbeschaedigt und befahrbar. Die \
+        Truppe ist in guter Verfassung. Weitere Meldung folgt am Abend.",
+    ];
+
+    fn messages_of_a_day() -> Vec<Message> {
+        let plugboard = Plugboard::from_file(Path::new(plugboard::PATH));
+        let mut enigma = Enigma::new(RotorAssembly::new_default(), plugboard);
+        zip(KEYS, TEXTS)
+            .map(|(key, text)| {
+                enigma.set_positions(GRUNDSTELLUNG);
+                let message =
+                    Message::new(Indicator::new(key.to_string()), preprocess_for_enigma(text));
+                enigma.encrypt(message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_align() {
+        assert_eq!(align("ABCDE", "XCDX", 1), (4, 2));
+        assert_eq!(align("ABC", "ABCDEF", 0), (3, 3));
+    }
+
+    #[test]
+    fn test_weight_of_evidence() {
+        assert!(weight_of_evidence(100, 8) > 0.0);
+        assert!(weight_of_evidence(100, 3) < 0.0);
+        assert_eq!(weight_of_evidence(0, 0), 0.0);
+    }
+
+    #[test]
+    fn finds_depths() {
+        let messages = messages_of_a_day();
+        let alignments = best_alignments(&messages, 50);
+        assert_eq!(alignments.len(), 6);
+        let best = &alignments[0];
+        let fast_letters = [KEYS[best.first], KEYS[best.second]].map(|key| key.as_bytes()[0]);
+        assert_eq!(best.offset, (fast_letters[1] - fast_letters[0]) as usize);
+    }
+
+    #[test]
+    fn finds_right_rotor() {
+        let messages = messages_of_a_day();
+        let alignments = best_alignments(&messages, 50);
+        let distances = key_distances(&messages, &alignments, 10.0);
+        assert!(!distances.is_empty());
+        let chain = longest_chain(&messages, &distances);
+        assert_eq!(chain.conflicts, 0);
+        assert_eq!(chain.relative_keys.len(), KEYS.len());
+        let candidates =
+            right_rotor_candidates(Path::new(PATH), &chain, &distances, &CYPHER_ROTOR_IDS);
+        let fast_rotor = candidates
+            .iter()
+            .find(|candidate| candidate.rotor_id == "I")
+            .unwrap();
+        let key_of_first_message = (KEYS[0].as_bytes()[0] - b'A') as usize;
+        assert!(fast_rotor.keys.contains(&key_of_first_message));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::cryptoattack::EnigmaAttack;
+use crate::message::{preprocess_for_enigma, TEXT};
+use crate::rotorassembly::RotorAssembly;
+use crate::score::CribScorer;
+use crate::{plugboard, rotor, Enigma, Plugboard};
+use std::fmt::{Display, Formatter};
+use std::hint::black_box;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const SAMPLES: usize = 10;
+
+/// Runs closures repeatedly and measures how long one call takes.
+pub struct Harness {
+    sample_time: Duration,
+    filter: Option<String>,
+}
+
+pub struct Measurement {
+    pub name: String,
+    /// Median over all samples.
+    pub nanoseconds_per_iteration: f64,
+    /// Letters encoded per iteration, if the benchmark encodes text.
+    pub letters: Option<usize>,
+}
+
+impl Harness {
+    pub fn new(sample_time: Duration, filter: Option<String>) -> Self {
+        Harness {
+            sample_time,
+            filter,
+        }
+    }
+
+    /// Measures `f` unless the name does not contain the filter. The number of iterations per
+    /// sample is chosen such that each sample takes about `sample_time`.
+    pub fn bench<T>(&self, name: &str, mut f: impl FnMut() -> T) -> Option<Measurement> {
+        if let Some(filter) = &self.filter {
+            if !name.contains(filter.as_str()) {
+                return None;
+            }
+        }
+        let iterations = self.calibrate(&mut f);
+        let mut samples: Vec<f64> = (0..SAMPLES)
+            .map(|_| {
+                let start = Instant::now();
+                for _ in 0..iterations {
+                    black_box(f());
+                }
+                start.elapsed().as_nanos() as f64 / iterations as f64
+            })
+            .collect();
+        samples.sort_by(f64::total_cmp);
+        Some(Measurement {
+            name: name.to_string(),
+            nanoseconds_per_iteration: samples[SAMPLES / 2],
+            letters: None,
+        })
+    }
+
+    fn calibrate<T>(&self, f: &mut impl FnMut() -> T) -> u64 {
+        let mut iterations = 1;
+        loop {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                black_box(f());
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= self.sample_time || iterations >= u64::MAX / 2 {
+                return iterations;
+            }
+            let factor = if elapsed.is_zero() {
+                10.0
+            } else {
+                (self.sample_time.as_secs_f64() / elapsed.as_secs_f64()).clamp(1.5, 10.0)
+            };
+            iterations = (iterations as f64 * factor).ceil() as u64;
+        }
+    }
+}
+
+impl Measurement {
+    fn with_letters(mut self, letters: usize) -> Self {
+        self.letters = Some(letters);
+        self
+    }
+}
+
+impl Display for Measurement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{:<40} {:>14.1} ns/iter",
+            self.name, self.nanoseconds_per_iteration
+        ))?;
+        if let Some(letters) = self.letters {
+            let per_second = letters as f64 * 1e9 / self.nanoseconds_per_iteration;
+            f.write_fmt(format_args!("  {:>12.0} letters/s", per_second))?;
+        }
+        Ok(())
+    }
+}
+
+/// The benchmark suite. Only meaningful in release builds.
+pub fn run_benchmarks(harness: &Harness) -> Vec<Measurement> {
+    let rotor_path = Path::new(rotor::PATH);
+    let plugboard_path = Path::new(plugboard::PATH);
+    let message = preprocess_for_enigma(TEXT).repeat(10);
+    let mut result = Vec::new();
+
+    let mut assembly = RotorAssembly::new_default();
+    result.extend(harness.bench("encode letter", || assembly.encode_char('A')));
+
+    let mut assembly = RotorAssembly::new_default();
+    assembly.precompute_scrambler();
+    result.extend(harness.bench("encode letter, precomputed scrambler", || {
+        assembly.encode_char('A')
+    }));
+
+    let mut enigma = Enigma::new(
+        RotorAssembly::new_default(),
+        Plugboard::from_file(plugboard_path),
+    );
+    let measurement = harness.bench("encode message", || enigma.encode_message(&message));
+    result.extend(measurement.map(|m| m.with_letters(message.len())));
+
+    result.extend(harness.bench("build machine from rotor file", || {
+        let assembly = RotorAssembly::from_file(rotor_path, ["I", "II", "III"], "B");
+        Enigma::new(assembly, Plugboard::from_file(plugboard_path))
+    }));
+
+    let mut enigma = Enigma::new(
+        RotorAssembly::new_default(),
+        Plugboard::from_file(plugboard_path),
+    );
+    enigma.set_positions([7, 8, 21]);
+    let cyphertext = enigma.encode_message(&message[0..100]);
+    let scorer = CribScorer::new(message[0..13].to_string(), 0);
+    let mut enigma = Enigma::new(RotorAssembly::new_default(), Plugboard::new(vec![]));
+    enigma.precompute_scrambler();
+    let mut attack = EnigmaAttack::new(enigma, [0, 0, 0]);
+    let measurement = harness.bench("known plaintext search step", || {
+        attack.score_at([7, 8, 21], &cyphertext, &scorer)
+    });
+    result.extend(measurement.map(|m| m.with_letters(cyphertext.len())));
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::benchmark::*;
+
+    #[test]
+    fn measures() {
+        let harness = Harness::new(Duration::from_micros(100), None);
+        let measurement = harness.bench("sum", || (0..100).sum::<u64>()).unwrap();
+        assert!(measurement.nanoseconds_per_iteration > 0.0);
+    }
+
+    #[test]
+    fn filters() {
+        let harness = Harness::new(Duration::from_micros(100), Some("encode".to_string()));
+        assert!(harness.bench("search", || 1).is_none());
+        assert!(harness.bench("encode letter", || 1).is_some());
+    }
+}
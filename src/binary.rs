@@ -0,0 +1,86 @@
+use crate::alphabet::{from_index, get_position_in_alphabet, NUMBER_LETTERS_IN_ALPHABET};
+use crate::enigma::Enigma;
+use crate::message::ungroup;
+
+/// Every byte is written as two letters, the base 26 digits of its value, so `AA` is 0 and `JV`
+/// is 255.
+const LETTERS_PER_BYTE: usize = 2;
+
+pub fn bytes_to_letters(bytes: &[u8]) -> String {
+    let base = NUMBER_LETTERS_IN_ALPHABET as u8;
+    bytes
+        .iter()
+        .flat_map(|byte| [from_index(byte / base), from_index(byte % base)])
+        .collect()
+}
+
+/// Reverses [`bytes_to_letters`].
+pub fn letters_to_bytes(letters: &str) -> Vec<u8> {
+    let digits: Vec<usize> = letters.chars().map(get_position_in_alphabet).collect();
+    assert_eq!(
+        digits.len() % LETTERS_PER_BYTE,
+        0,
+        "every byte must be written as two letters"
+    );
+    digits
+        .chunks(LETTERS_PER_BYTE)
+        .map(|pair| {
+            let value = pair[0] * NUMBER_LETTERS_IN_ALPHABET + pair[1];
+            u8::try_from(value).unwrap_or_else(|_| panic!("{} is not a byte", value))
+        })
+        .collect()
+}
+
+/// Enciphers `bytes` written as letters.
+pub fn encrypt_bytes(enigma: &mut Enigma, bytes: &[u8]) -> String {
+    enigma.encode_message(&bytes_to_letters(bytes))
+}
+
+/// Deciphers the output of [`encrypt_bytes`], which may be written in groups.
+pub fn decrypt_bytes(enigma: &mut Enigma, cyphertext: &str) -> Vec<u8> {
+    letters_to_bytes(&enigma.encode_message(&ungroup(cyphertext)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::binary::*;
+    use crate::message::GROUPS;
+    use crate::rotorassembly::RotorAssembly;
+    use crate::{plugboard, Plugboard};
+    use std::path::Path;
+
+    fn enigma() -> Enigma {
+        let plugboard = Plugboard::from_file(Path::new(plugboard::PATH));
+        Enigma::new(RotorAssembly::new_default(), plugboard)
+    }
+
+    #[test]
+    fn letters_of_bytes() {
+        assert_eq!(bytes_to_letters(&[0, 1, 26, 255]), "AAABBAJV");
+        assert_eq!(letters_to_bytes("AAABBAJV"), vec![0, 1, 26, 255]);
+        assert_eq!(bytes_to_letters(&[]), "");
+    }
+
+    #[test]
+    fn all_bytes_survive_the_machine() {
+        let bytes: Vec<u8> = (0..=255).chain((0..=255).rev()).collect();
+        let cyphertext = encrypt_bytes(&mut enigma(), &bytes);
+        assert_ne!(cyphertext, bytes_to_letters(&bytes));
+        assert_eq!(
+            decrypt_bytes(&mut enigma(), &GROUPS.format(&cyphertext)),
+            bytes
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn value_too_large() {
+        letters_to_bytes("JW");
+    }
+
+    #[test]
+    #[should_panic]
+    fn odd_number_of_letters() {
+        letters_to_bytes("AAA");
+    }
+}
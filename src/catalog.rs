@@ -0,0 +1,216 @@
+use crate::alphabet::{get_position_in_alphabet, letters_from_positions};
+use crate::parallel::map_in_parallel;
+use crate::rejewski::Characteristic;
+use crate::rotorassembly::{all_positions, parse_wheel_order, RotorAssembly, WheelOrder};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const INDEX_FILE: &str = "index.txt";
+const SETTINGS_FILE: &str = "settings.txt";
+const REFLECTOR_HEADER: &str = "# reflector ";
+
+/// A wheel order together with the positions at which the indicators were enciphered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Setting {
+    pub wheel_order: WheelOrder,
+    pub positions: [usize; 3],
+}
+
+pub trait SettingLookup {
+    fn lookup(&self, characteristic: &Characteristic) -> Vec<Setting>;
+}
+
+/// Characteristics of all positions for a set of wheel orders, the "card catalog".
+pub struct Catalog {
+    reflector_id: String,
+    entries: HashMap<Characteristic, Vec<Setting>>,
+}
+
+/// A catalog written by [`Catalog::write`]. Only the index is held in memory, the settings are
+/// read from disk on every lookup.
+pub struct DiskCatalog {
+    reflector_id: String,
+    settings_path: PathBuf,
+    index: HashMap<Characteristic, (u64, usize)>,
+}
+
+impl Setting {
+    /// Parses a setting written as `I-II-III QRS`.
+    pub fn parse(line: &str) -> Setting {
+        let (wheel_order, positions) = line
+            .split_once(' ')
+            .expect("setting must consist of wheel order and positions");
+        let positions: Vec<usize> = positions.chars().map(get_position_in_alphabet).collect();
+        Setting {
+            wheel_order: parse_wheel_order(wheel_order),
+            positions: positions
+                .try_into()
+                .expect("setting must have three positions"),
+        }
+    }
+}
+
+impl Display for Setting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} {}",
+            self.wheel_order.join("-"),
+            letters_from_positions(&self.positions)
+        ))
+    }
+}
+
+impl Catalog {
+    /// Computes the characteristic of every position for every wheel order. The wheel orders
+    /// are distributed over all available cores.
+    pub fn generate(path: &Path, wheel_orders: &[WheelOrder], reflector_id: &str) -> Catalog {
+        let parts = map_in_parallel(wheel_orders, |wheel_order| {
+            characteristics(path, *wheel_order, reflector_id)
+        });
+        let mut entries: HashMap<Characteristic, Vec<Setting>> = HashMap::new();
+        for (characteristic, setting) in parts.into_iter().flatten() {
+            entries.entry(characteristic).or_default().push(setting);
+        }
+        Catalog {
+            reflector_id: reflector_id.to_string(),
+            entries,
+        }
+    }
+
+    /// Writes the settings grouped by characteristic to one file and the byte offset of each
+    /// group to an index file, both into `directory`.
+    pub fn write(&self, directory: &Path) {
+        fs::create_dir_all(directory).unwrap();
+        let mut signatures: Vec<(String, &Vec<Setting>)> = self
+            .entries
+            .iter()
+            .map(|(characteristic, settings)| (characteristic.signature(), settings))
+            .collect();
+        signatures.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut settings_file =
+            BufWriter::new(File::create(directory.join(SETTINGS_FILE)).unwrap());
+        let mut index_file = BufWriter::new(File::create(directory.join(INDEX_FILE)).unwrap());
+        writeln!(index_file, "{}{}", REFLECTOR_HEADER, self.reflector_id).unwrap();
+        let mut offset = 0;
+        for (signature, settings) in signatures {
+            writeln!(index_file, "{} {} {}", signature, offset, settings.len()).unwrap();
+            for setting in settings {
+                let line = format!("{}\n", setting);
+                settings_file.write_all(line.as_bytes()).unwrap();
+                offset += line.len();
+            }
+        }
+        settings_file.flush().unwrap();
+        index_file.flush().unwrap();
+    }
+}
+
+impl SettingLookup for Catalog {
+    fn lookup(&self, characteristic: &Characteristic) -> Vec<Setting> {
+        self.entries
+            .get(characteristic)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl DiskCatalog {
+    pub fn open(directory: &Path) -> DiskCatalog {
+        let contents = fs::read_to_string(directory.join(INDEX_FILE)).unwrap();
+        let mut lines = contents.lines();
+        let reflector_id = lines
+            .next()
+            .and_then(|line| line.strip_prefix(REFLECTOR_HEADER))
+            .expect("catalog index must start with the reflector")
+            .to_string();
+        let index = lines.map(parse_index_line).collect();
+        DiskCatalog {
+            reflector_id,
+            settings_path: directory.join(SETTINGS_FILE),
+            index,
+        }
+    }
+
+    pub fn reflector_id(&self) -> &str {
+        &self.reflector_id
+    }
+}
+
+impl SettingLookup for DiskCatalog {
+    fn lookup(&self, characteristic: &Characteristic) -> Vec<Setting> {
+        let Some((offset, count)) = self.index.get(characteristic) else {
+            return Vec::new();
+        };
+        let mut file = File::open(&self.settings_path).unwrap();
+        file.seek(SeekFrom::Start(*offset)).unwrap();
+        BufReader::new(file)
+            .lines()
+            .take(*count)
+            .map(|line| Setting::parse(&line.unwrap()))
+            .collect()
+    }
+}
+
+fn parse_index_line(line: &str) -> (Characteristic, (u64, usize)) {
+    let mut items = line.split(' ');
+    let mut next_item = || items.next().expect("catalog index line is incomplete");
+    let characteristic = Characteristic::from_signature(next_item());
+    let offset = next_item().parse().unwrap();
+    let count = next_item().parse().unwrap();
+    (characteristic, (offset, count))
+}
+
+fn characteristics(
+    path: &Path,
+    wheel_order: WheelOrder,
+    reflector_id: &str,
+) -> Vec<(Characteristic, Setting)> {
+    let mut assembly = RotorAssembly::from_file(path, wheel_order, reflector_id);
+    assembly.precompute_scrambler();
+    all_positions()
+        .map(|positions| {
+            let characteristic = Characteristic::from_assembly(&mut assembly, positions);
+            let setting = Setting {
+                wheel_order,
+                positions,
+            };
+            (characteristic, setting)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::catalog::*;
+    use crate::rotor::PATH;
+    use std::env;
+
+    #[test]
+    fn parse_setting() {
+        let setting = Setting::parse("II-V-I QRS");
+        assert_eq!(setting.wheel_order, ["II", "V", "I"]);
+        assert_eq!(setting.positions, [16, 17, 18]);
+        assert_eq!(setting.to_string(), "II-V-I QRS");
+    }
+
+    #[test]
+    fn disk_catalog_matches_catalog() {
+        let catalog = Catalog::generate(Path::new(PATH), &[["III", "I", "II"]], "B");
+        let directory = env::temp_dir().join(format!("enigma-catalog-{}", std::process::id()));
+        catalog.write(&directory);
+        let disk_catalog = DiskCatalog::open(&directory);
+        assert_eq!(disk_catalog.reflector_id(), "B");
+        assert_eq!(disk_catalog.index.len(), catalog.entries.len());
+        for characteristic in catalog.entries.keys() {
+            assert_eq!(
+                disk_catalog.lookup(characteristic),
+                catalog.lookup(characteristic)
+            );
+        }
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,346 @@
+use crate::alphabet::letters_from_positions;
+use crate::cryptoattack::{Key, KeySpace, ScoredKey, SearchEvent};
+use crate::rotorassembly::{parse_ring_settings, parse_wheel_order, REFLECTOR_IDS};
+use crate::score::Scorer;
+use std::fs;
+use std::path::Path;
+
+const HEADER: &str = "# enigma key search checkpoint";
+
+/// Progress of a key search that can be saved to a file and resumed from, see
+/// [`crate::cryptoattack::resume_key_search`]. The file records everything the search depends
+/// on, the searched units as ranges and the candidates found in them:
+///
+/// ```text
+/// # enigma key search checkpoint
+/// cyphertext SABXSVCRDK
+/// scorer crib WETTER 3
+/// threshold 0.75
+/// wheel-orders I-II-III III-II-I
+/// reflectors B C
+/// rings AAA BAA
+/// done 0-5 7
+/// candidate 0.85 B I-II-III ring AAA position QRS
+/// ```
+pub struct Checkpoint {
+    cyphertext: String,
+    scorer: String,
+    threshold: f64,
+    key_space: KeySpace,
+    done: Vec<bool>,
+    pub candidates: Vec<ScoredKey>,
+}
+
+impl Checkpoint {
+    pub fn new(
+        key_space: &KeySpace,
+        cyphertext: &str,
+        scorer: &dyn Scorer,
+        threshold: f64,
+    ) -> Checkpoint {
+        Checkpoint {
+            cyphertext: cyphertext.to_string(),
+            scorer: scorer.describe(),
+            threshold,
+            key_space: key_space.clone(),
+            done: vec![false; key_space.units()],
+            candidates: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Checkpoint {
+        let contents = fs::read_to_string(path).unwrap();
+        let mut lines = contents.lines();
+        assert_eq!(lines.next(), Some(HEADER), "not a checkpoint file");
+        let mut cyphertext = None;
+        let mut scorer = None;
+        let mut threshold = None;
+        let mut wheel_orders = None;
+        let mut reflector_ids = None;
+        let mut ring_settings = None;
+        let mut done = Vec::new();
+        let mut candidates = Vec::new();
+        for line in lines {
+            let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+            let items = value.split(' ').filter(|item| !item.is_empty());
+            match name {
+                "cyphertext" => cyphertext = Some(value.to_string()),
+                "scorer" => scorer = Some(value.to_string()),
+                "threshold" => threshold = Some(value.parse().expect("invalid threshold")),
+                "wheel-orders" => wheel_orders = Some(items.map(parse_wheel_order).collect()),
+                "reflectors" => reflector_ids = Some(items.map(parse_reflector).collect()),
+                "rings" => ring_settings = Some(items.map(parse_ring_settings).collect()),
+                "done" => done.extend(items.flat_map(parse_range)),
+                "candidate" => {
+                    let (score, key) = value.split_once(' ').expect("candidate without key");
+                    candidates.push(ScoredKey {
+                        key: Key::parse(key),
+                        score: score.parse().expect("invalid score"),
+                    });
+                }
+                _ => panic!("invalid checkpoint line {}", line),
+            }
+        }
+        let key_space = KeySpace {
+            wheel_orders: wheel_orders.expect("checkpoint without wheel orders"),
+            reflector_ids: reflector_ids.expect("checkpoint without reflectors"),
+            ring_settings: ring_settings.expect("checkpoint without ring settings"),
+        };
+        let mut checkpoint = Checkpoint {
+            cyphertext: cyphertext.expect("checkpoint without cyphertext"),
+            scorer: scorer.expect("checkpoint without scorer"),
+            threshold: threshold.expect("checkpoint without threshold"),
+            done: vec![false; key_space.units()],
+            key_space,
+            candidates,
+        };
+        for unit in done {
+            checkpoint.done[unit] = true;
+        }
+        checkpoint
+    }
+
+    /// Writes to a temporary file first, so that a crash while saving keeps the old checkpoint.
+    /// Only candidates of units that were searched completely are written, an interrupted unit
+    /// is searched again on resume and finds its candidates again.
+    pub fn save(&self, path: &Path) {
+        let wheel_orders: Vec<String> = self
+            .key_space
+            .wheel_orders
+            .iter()
+            .map(|wheel_order| wheel_order.join("-"))
+            .collect();
+        let ring_settings: Vec<String> = self
+            .key_space
+            .ring_settings
+            .iter()
+            .map(|ring_settings| letters_from_positions(ring_settings))
+            .collect();
+        let mut contents = format!(
+            "{}\ncyphertext {}\nscorer {}\nthreshold {}\nwheel-orders {}\nreflectors {}\n\
+            rings {}\ndone {}\n",
+            HEADER,
+            self.cyphertext,
+            self.scorer,
+            self.threshold,
+            wheel_orders.join(" "),
+            self.key_space.reflector_ids.join(" "),
+            ring_settings.join(" "),
+            format_ranges(&self.done)
+        );
+        for candidate in self.candidates.iter().filter(|candidate| {
+            self.key_space
+                .unit(&candidate.key)
+                .is_some_and(|unit| self.done[unit])
+        }) {
+            contents.push_str(&format!(
+                "candidate {} {}\n",
+                candidate.score, candidate.key
+            ));
+        }
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, contents).unwrap();
+        fs::rename(&temporary, path).unwrap();
+    }
+
+    /// Updates the checkpoint with an event of the search.
+    pub fn record(&mut self, event: &SearchEvent) {
+        match event {
+            SearchEvent::Candidate { candidate, .. } => self.candidates.push(*candidate),
+            SearchEvent::Progress { unit, .. } => self.done[*unit] = true,
+        }
+    }
+
+    pub fn done(&self) -> &[bool] {
+        &self.done
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.done.iter().all(|done| *done)
+    }
+
+    /// Panics unless the checkpoint was saved by a search with the same parameters.
+    pub fn check_matches(
+        &self,
+        key_space: &KeySpace,
+        cyphertext: &str,
+        scorer: &dyn Scorer,
+        threshold: f64,
+    ) {
+        assert_eq!(
+            &self.key_space, key_space,
+            "checkpoint was saved for another key space"
+        );
+        assert_eq!(
+            self.cyphertext, cyphertext,
+            "checkpoint was saved for another cyphertext"
+        );
+        assert_eq!(
+            self.scorer,
+            scorer.describe(),
+            "checkpoint was saved for another scorer"
+        );
+        assert_eq!(
+            self.threshold, threshold,
+            "checkpoint was saved for another threshold"
+        );
+    }
+}
+
+fn parse_reflector(id: &str) -> &'static str {
+    REFLECTOR_IDS
+        .iter()
+        .find(|known| **known == id)
+        .unwrap_or_else(|| panic!("unknown reflector {}", id))
+}
+
+fn parse_range(range: &str) -> std::ops::RangeInclusive<usize> {
+    let parse = |value: &str| value.parse().expect("invalid unit");
+    match range.split_once('-') {
+        Some((first, last)) => parse(first)..=parse(last),
+        None => parse(range)..=parse(range),
+    }
+}
+
+fn format_ranges(done: &[bool]) -> String {
+    let mut ranges = Vec::new();
+    let mut unit = 0;
+    while unit < done.len() {
+        if !done[unit] {
+            unit += 1;
+            continue;
+        }
+        let first = unit;
+        while unit + 1 < done.len() && done[unit + 1] {
+            unit += 1;
+        }
+        ranges.push(if first == unit {
+            first.to_string()
+        } else {
+            format!("{}-{}", first, unit)
+        });
+        unit += 1;
+    }
+    ranges.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checkpoint::*;
+    use crate::score::CribScorer;
+    use std::env;
+    use std::path::PathBuf;
+    use std::process;
+
+    const THRESHOLD: f64 = 0.75;
+
+    fn key_space() -> KeySpace {
+        KeySpace {
+            wheel_orders: vec![["I", "II", "III"], ["III", "II", "I"]],
+            reflector_ids: vec!["B", "C"],
+            ring_settings: vec![[0, 0, 0], [1, 0, 0]],
+        }
+    }
+
+    fn scorer() -> CribScorer {
+        CribScorer::new("WETTER".to_string(), 3)
+    }
+
+    fn checkpoint() -> Checkpoint {
+        Checkpoint::new(&key_space(), "ABCDEF", &scorer(), THRESHOLD)
+    }
+
+    /// Includes the process id, so that parallel test runs do not share files.
+    fn temporary_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("enigma-checkpoint-{}-{}.txt", name, process::id()))
+    }
+
+    fn progress(unit: usize) -> SearchEvent {
+        SearchEvent::Progress {
+            unit,
+            searched: 0,
+            total: 0,
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        let done = [true, true, false, true, false, false, true, true, true];
+        assert_eq!(format_ranges(&done), "0-1 3 6-8");
+        let units: Vec<usize> = "0-1 3 6-8".split(' ').flat_map(parse_range).collect();
+        assert_eq!(units, vec![0, 1, 3, 6, 7, 8]);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut checkpoint = checkpoint();
+        let candidate = ScoredKey {
+            key: Key::parse("C III-II-I ring BAA position QRS"),
+            score: 0.75,
+        };
+        assert_eq!(key_space().unit(&candidate.key), Some(7));
+        checkpoint.record(&SearchEvent::Candidate {
+            scorer: 0,
+            candidate,
+        });
+        for unit in [0, 1, 2, 5, 7] {
+            checkpoint.record(&progress(unit));
+        }
+        let path = temporary_path("save-and-load");
+        checkpoint.save(&path);
+
+        let loaded = Checkpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+        loaded.check_matches(&key_space(), "ABCDEF", &scorer(), THRESHOLD);
+        assert_eq!(loaded.done(), checkpoint.done());
+        assert_eq!(loaded.candidates, vec![candidate]);
+        assert!(!loaded.is_complete());
+    }
+
+    #[test]
+    fn candidates_of_interrupted_units_are_not_saved() {
+        let mut checkpoint = checkpoint();
+        let candidate = ScoredKey {
+            key: Key::parse("B I-II-III ring BAA position QRS"),
+            score: 0.8,
+        };
+        checkpoint.record(&SearchEvent::Candidate {
+            scorer: 0,
+            candidate,
+        });
+        checkpoint.record(&progress(0));
+        let path = temporary_path("interrupted");
+        checkpoint.save(&path);
+
+        let loaded = Checkpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.candidates.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn other_cyphertext() {
+        checkpoint().check_matches(&key_space(), "ABCDEG", &scorer(), THRESHOLD);
+    }
+
+    #[test]
+    #[should_panic]
+    fn other_crib_offset() {
+        let scorer = CribScorer::new("WETTER".to_string(), 4);
+        checkpoint().check_matches(&key_space(), "ABCDEF", &scorer, THRESHOLD);
+    }
+
+    #[test]
+    #[should_panic]
+    fn other_key_space_of_the_same_size() {
+        let mut key_space = key_space();
+        key_space.reflector_ids = vec!["C", "B"];
+        checkpoint().check_matches(&key_space, "ABCDEF", &scorer(), THRESHOLD);
+    }
+
+    #[test]
+    #[should_panic]
+    fn other_threshold() {
+        checkpoint().check_matches(&key_space(), "ABCDEF", &scorer(), 0.5);
+    }
+}
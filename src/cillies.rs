@@ -0,0 +1,150 @@
+use crate::alphabet::letters_from_positions;
+use crate::enigma::Enigma;
+use crate::message::{data_lines, Indicator};
+use crate::rotorassembly::all_positions;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+pub const PATH: &str = "src/cillies.txt";
+/// Dictionary entry for a message key equal to the Grundstellung.
+const GRUNDSTELLUNG: &str = "=";
+
+/// Message keys that operators were likely to choose.
+pub struct Dictionary {
+    keys: HashSet<String>,
+    grundstellung: bool,
+}
+
+/// A Grundstellung at which some indicators decrypt to likely message keys.
+pub struct CillieCandidate {
+    pub grundstellung: [usize; 3],
+    /// Index of the indicator and the message key it decrypts to.
+    pub hits: Vec<(usize, String)>,
+}
+
+impl Dictionary {
+    /// Reads one message key per line, see [`data_lines`].
+    pub fn from_file(path: &Path) -> Dictionary {
+        let mut keys = HashSet::new();
+        let mut grundstellung = false;
+        for line in data_lines(path) {
+            if line == GRUNDSTELLUNG {
+                grundstellung = true;
+            } else {
+                assert_eq!(
+                    line.len(),
+                    3,
+                    "message key {} must have three letters",
+                    line
+                );
+                keys.insert(line);
+            }
+        }
+        Dictionary {
+            keys,
+            grundstellung,
+        }
+    }
+
+    fn contains(&self, key: &str, grundstellung: &str) -> bool {
+        self.keys.contains(key) || (self.grundstellung && key == grundstellung)
+    }
+}
+
+impl Display for CillieCandidate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let keys: Vec<&str> = self.hits.iter().map(|(_, key)| key.as_str()).collect();
+        f.write_fmt(format_args!(
+            "{}  {} hits: {}",
+            letters_from_positions(&self.grundstellung),
+            self.hits.len(),
+            keys.join(" ")
+        ))
+    }
+}
+
+/// Tries every Grundstellung for the indicators of one day, which were all enciphered at it.
+/// An indicator counts as a hit if it decrypts to a doubled message key from the dictionary.
+/// Returns the positions with at least `min_hits` hits, most hits first. `enigma` needs the
+/// plugboard of the day, indicators with plugged letters only decrypt with it.
+pub fn cillie_attack(
+    enigma: &Enigma,
+    indicators: &[Indicator],
+    dictionary: &Dictionary,
+    min_hits: usize,
+) -> Vec<CillieCandidate> {
+    let mut enigma = enigma.clone();
+    enigma.precompute_scrambler();
+    let mut result: Vec<CillieCandidate> = all_positions()
+        .filter_map(|grundstellung| {
+            let grundstellung_letters = letters_from_positions(&grundstellung);
+            let hits: Vec<(usize, String)> = indicators
+                .iter()
+                .enumerate()
+                .filter_map(|(i, indicator)| {
+                    enigma.set_positions(grundstellung);
+                    let first = enigma.encode_message(indicator.get_first_triplet());
+                    let second = enigma.encode_message(indicator.get_second_triplet());
+                    (first == second && dictionary.contains(&first, &grundstellung_letters))
+                        .then_some((i, first))
+                })
+                .collect();
+            (hits.len() >= min_hits).then_some(CillieCandidate {
+                grundstellung,
+                hits,
+            })
+        })
+        .collect();
+    result.sort_by_key(|candidate| std::cmp::Reverse(candidate.hits.len()));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cillies::*;
+    use crate::message::Message;
+    use crate::rotorassembly::RotorAssembly;
+    use crate::{plugboard, Plugboard};
+
+    const GRUNDSTELLUNG: [usize; 3] = [11, 2, 19];
+
+    #[test]
+    fn finds_grundstellung_from_lazy_keys() {
+        let plugboard = Plugboard::from_file(Path::new(plugboard::PATH));
+        let mut enigma = Enigma::new(RotorAssembly::new_default(), plugboard);
+        let keys = ["AAA", "QWE", "LCT", "KJU", "PYX", "ZZZ", "FHR", "ABC"];
+        let indicators: Vec<Indicator> = keys
+            .iter()
+            .map(|key| {
+                enigma.set_positions(GRUNDSTELLUNG);
+                let message = Message::new(Indicator::new(key.to_string()), String::new());
+                enigma.encrypt(message).indicator
+            })
+            .collect();
+
+        let dictionary = Dictionary::from_file(Path::new(PATH));
+        let candidates = cillie_attack(&enigma, &indicators, &dictionary, 3);
+        let best = &candidates[0];
+        assert_eq!(best.grundstellung, GRUNDSTELLUNG);
+        // LCT is the Grundstellung itself, KJU and FHR are not in the dictionary
+        let hits: Vec<usize> = best.hits.iter().map(|(i, _)| *i).collect();
+        assert_eq!(hits, vec![0, 1, 2, 4, 5, 7]);
+
+        // every hit has plugged letters in its key or indicator, so none is found without the
+        // plugboard of the day
+        enigma.set_plugboard(&Plugboard::new(vec![]));
+        let candidates = cillie_attack(&enigma, &indicators, &dictionary, 1);
+        assert!(candidates
+            .iter()
+            .all(|candidate| candidate.grundstellung != GRUNDSTELLUNG));
+    }
+
+    #[test]
+    fn dictionary() {
+        let dictionary = Dictionary::from_file(Path::new(PATH));
+        assert!(dictionary.contains("AAA", "QRS"));
+        assert!(dictionary.contains("QRS", "QRS"));
+        assert!(!dictionary.contains("KJU", "QRS"));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::alphabet::{is_capital_letter, normalise};
+use std::fmt::{Display, Formatter};
+
+pub const WILDCARD: char = '?';
+const START_ANCHOR: char = '^';
+const END_ANCHOR: char = '$';
+
+/// How the digits were spelled out, including the usual abbreviations.
+pub const NUMBER_SPELLINGS: [&[&str]; 10] = [
+    &["NULL"],
+    &["EINS", "EIN"],
+    &["ZWO", "ZWEI"],
+    &["DREI"],
+    &["VIER"],
+    &["FUENF", "FUNF"],
+    &["SECHS", "SEQS"],
+    &["SIEBEN"],
+    &["ACHT"],
+    &["NEUN"],
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    Anywhere,
+    Start,
+    End,
+}
+
+/// A guessed piece of plaintext, written like `^ANX`, `WETTER?ORHERSAGE` or `WETTER0600$`.
+/// `?` matches any letter, digits stand for all their spellings, `^` and `$` anchor the crib
+/// at the start or the end of the message.
+#[derive(Clone, Debug)]
+pub struct Crib {
+    spec: String,
+    anchor: Anchor,
+    /// Every spelling of the crib, `None` is a wildcard.
+    variants: Vec<Vec<Option<char>>>,
+}
+
+/// A spelling of a crib at an offset where no letter would encrypt to itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub variant: String,
+    pub offset: usize,
+}
+
+pub struct CribPlacements {
+    pub crib: String,
+    pub placements: Vec<Placement>,
+}
+
+impl Crib {
+    /// Capitalises the letters of a crib typed by a user, see [`normalise`], and drops anything
+    /// but letters and the crib syntax.
+    pub fn normalise_spec(spec: &str) -> String {
+        let mut result = String::new();
+        for c in spec.chars() {
+            match c {
+                WILDCARD | START_ANCHOR | END_ANCHOR | '0'..='9' => result.push(c),
+                _ => result.extend(normalise(c)),
+            }
+        }
+        result
+    }
+
+    pub fn parse(spec: &str) -> Crib {
+        let mut pattern = spec;
+        let mut anchor = Anchor::Anywhere;
+        if let Some(rest) = pattern.strip_prefix(START_ANCHOR) {
+            pattern = rest;
+            anchor = Anchor::Start;
+        }
+        if let Some(rest) = pattern.strip_suffix(END_ANCHOR) {
+            assert_eq!(anchor, Anchor::Anywhere, "crib cannot be anchored twice");
+            pattern = rest;
+            anchor = Anchor::End;
+        }
+        assert!(!pattern.is_empty(), "crib must not be empty");
+
+        let mut variants = vec![Vec::new()];
+        for char in pattern.chars() {
+            let parts: Vec<Vec<Option<char>>> = match char {
+                WILDCARD => vec![vec![None]],
+                c if c.is_ascii_digit() => NUMBER_SPELLINGS[c.to_digit(10).unwrap() as usize]
+                    .iter()
+                    .map(|spelling| spelling.chars().map(Some).collect())
+                    .collect(),
+                c => {
+                    assert!(is_capital_letter(c), "invalid character {} in crib", c);
+                    vec![vec![Some(c)]]
+                }
+            };
+            variants = variants
+                .iter()
+                .flat_map(|variant| {
+                    parts.iter().map(move |part| {
+                        let mut variant = variant.clone();
+                        variant.extend(part);
+                        variant
+                    })
+                })
+                .collect();
+        }
+        Crib {
+            spec: spec.to_string(),
+            anchor,
+            variants,
+        }
+    }
+
+    /// Drags the crib along the cyphertext. A placement survives if no letter of it lies on the
+    /// same letter of the cyphertext, since the Enigma never encrypts a letter to itself.
+    pub fn placements(&self, cyphertext: &str) -> Vec<Placement> {
+        let cyphertext: Vec<char> = cyphertext.chars().collect();
+        let mut result = Vec::new();
+        for variant in &self.variants {
+            if variant.len() > cyphertext.len() {
+                continue;
+            }
+            let last = cyphertext.len() - variant.len();
+            let offsets = match self.anchor {
+                Anchor::Anywhere => 0..=last,
+                Anchor::Start => 0..=0,
+                Anchor::End => last..=last,
+            };
+            for offset in offsets {
+                if fits(variant, &cyphertext[offset..]) {
+                    result.push(Placement {
+                        variant: variant_to_string(variant),
+                        offset,
+                    });
+                }
+            }
+        }
+        result.sort_by_key(|placement| placement.offset);
+        result
+    }
+}
+
+impl Display for Placement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{:>4}  {}", self.offset, self.variant))
+    }
+}
+
+/// All surviving placements of every crib.
+pub fn drag_cribs(cyphertext: &str, cribs: &[Crib]) -> Vec<CribPlacements> {
+    cribs
+        .iter()
+        .map(|crib| CribPlacements {
+            crib: crib.spec.clone(),
+            placements: crib.placements(cyphertext),
+        })
+        .collect()
+}
+
+fn fits(variant: &[Option<char>], cyphertext: &[char]) -> bool {
+    variant
+        .iter()
+        .zip(cyphertext)
+        .all(|(plain, cypher)| *plain != Some(*cypher))
+}
+
+fn variant_to_string(variant: &[Option<char>]) -> String {
+    variant.iter().map(|c| c.unwrap_or(WILDCARD)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crib::*;
+
+    #[test]
+    fn exact_crib() {
+        let crib = Crib::parse("AB");
+        let offsets: Vec<usize> = crib.placements("ABCAB").iter().map(|p| p.offset).collect();
+        // A on A at 0 and 3, B on B at 4 is not possible either
+        assert_eq!(offsets, vec![1, 2]);
+    }
+
+    #[test]
+    fn wildcard_matches_any_letter() {
+        let crib = Crib::parse("A?C");
+        let placements = crib.placements("XBX");
+        assert_eq!(
+            placements,
+            vec![Placement {
+                variant: "A?C".to_string(),
+                offset: 0
+            }]
+        );
+        assert!(crib.placements("AXX").is_empty());
+    }
+
+    #[test]
+    fn numbers_are_spelled_out() {
+        let crib = Crib::parse("X26");
+        let variants: Vec<String> = crib.variants.iter().map(|v| variant_to_string(v)).collect();
+        assert_eq!(
+            variants,
+            vec!["XZWOSECHS", "XZWOSEQS", "XZWEISECHS", "XZWEISEQS"]
+        );
+    }
+
+    #[test]
+    fn normalise_typed_crib() {
+        assert_eq!(Crib::normalise_spec("^wetter 06?0$"), "^WETTER06?0$");
+        assert_eq!(Crib::normalise_spec("Straße"), "STRASSE");
+    }
+
+    #[test]
+    fn anchors() {
+        let start = Crib::parse("^AN");
+        assert_eq!(start.placements("XXXXX")[0].offset, 0);
+        assert_eq!(start.placements("XXXXX").len(), 1);
+        assert!(start.placements("AXXXX").is_empty());
+
+        let end = Crib::parse("AN$");
+        assert_eq!(end.placements("XXXXX")[0].offset, 3);
+        assert_eq!(end.placements("XXXXX").len(), 1);
+    }
+
+    #[test]
+    fn crib_longer_than_text() {
+        assert!(Crib::parse("WETTER").placements("ABC").is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_character() {
+        Crib::parse("wetter");
+    }
+
+    #[test]
+    fn drags_several_cribs() {
+        let cribs = [Crib::parse("WETTER"), Crib::parse("ANX$")];
+        let result = drag_cribs("QWERTZUIOPASDFG", &cribs);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].crib, "WETTER");
+        assert_eq!(result[1].placements[0].offset, 12);
+    }
+}
@@ -1,122 +1,1047 @@
-use crate::alphabet::NUMBER_LETTERS_IN_ALPHABET;
+use crate::alphabet::{
+    get_position_in_alphabet, letters_from_positions, ALPHABET, NUMBER_LETTERS_IN_ALPHABET,
+};
+use crate::checkpoint::Checkpoint;
+use crate::crib::{Crib, Placement};
 use crate::message::Message;
-use crate::rotorassembly::RotorAssembly;
+use crate::plugboard::{Pair, MAX_PAIRS};
+use crate::random::Random;
+use crate::report::{AttackReport, KeyCandidate, Timing};
+use crate::rotorassembly::{
+    all_positions, parse_wheel_order, MachineModel, RotorAssembly, WheelOrder, REFLECTOR_IDS,
+};
+use crate::score::{CribScorer, Scorer};
 use crate::{rotor, Enigma, Plugboard};
-use std::iter::zip;
+use std::fmt::{Display, Formatter};
+use std::num::NonZeroUsize;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
 
-struct EnigmaAttack {
+const NUMBER_OF_POSITIONS: usize =
+    NUMBER_LETTERS_IN_ALPHABET * NUMBER_LETTERS_IN_ALPHABET * NUMBER_LETTERS_IN_ALPHABET;
+const PLUGBOARD_RESTARTS: usize = 3;
+const SOLVED_CRIB_OFFSETS: usize = 3;
+const PREVIEW_LENGTH: usize = 40;
+const KEYS_PER_PLACEMENT: usize = 2;
+const KEY_THRESHOLD: f64 = 0.2;
+const PLUGBOARD_SEED: u64 = 1;
+
+pub struct EnigmaAttack {
     enigma: Enigma,
     positions: [usize; 3],
 }
 
 impl EnigmaAttack {
-    fn new(enigma: Enigma, positions: [usize; 3]) -> EnigmaAttack {
+    pub fn new(enigma: Enigma, positions: [usize; 3]) -> EnigmaAttack {
         for pos in &positions {
             assert!(pos < &NUMBER_LETTERS_IN_ALPHABET);
         }
         EnigmaAttack { enigma, positions }
     }
 
-    fn new_default() -> EnigmaAttack {
-        // order of rotors is correct, positions are not
-        let plugboard = Plugboard::new(vec![]);
-        let rotor_path = Path::new(rotor::PATH);
-        let rotors = vec![
-            rotor::Rotor::from_file(rotor_path, "I"),
-            rotor::Rotor::from_file(rotor_path, "II"),
-            rotor::Rotor::from_file(rotor_path, "III"),
-        ];
-        let reflector = rotor::Reflector::from_file(rotor_path, "B");
-        let assembly = RotorAssembly::new(rotors, reflector);
-        EnigmaAttack::new(Enigma::new(assembly, plugboard), [0, 0, 0])
-    }
-
     fn reset_positions(&mut self) {
         self.enigma.set_positions(self.positions);
     }
+
+    fn decrypt_at(&mut self, positions: [usize; 3], cyphertext: &str) -> String {
+        self.positions = positions;
+        self.reset_positions();
+        self.enigma.encode_message(cyphertext)
+    }
+
+    fn set_plugboard(&mut self, plugboard: &Plugboard) {
+        self.enigma.set_plugboard(plugboard);
+    }
+
+    /// One step of a key search: decrypts at `positions` and scores the result.
+    pub fn score_at(
+        &mut self,
+        positions: [usize; 3],
+        cyphertext: &str,
+        scorer: &dyn Scorer,
+    ) -> f64 {
+        let text = self.decrypt_at(positions, cyphertext);
+        scorer.score(&text)
+    }
+}
+
+/// A complete machine setting apart from the plugboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    pub wheel_order: WheelOrder,
+    pub reflector: &'static str,
+    pub ring_settings: [usize; 3],
+    pub positions: [usize; 3],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ScoredKey {
+    pub key: Key,
+    pub score: f64,
+}
+
+pub enum SearchEvent {
+    /// A key that one of the scorers, given by its index, rated at least at the threshold.
+    Candidate { scorer: usize, candidate: ScoredKey },
+    /// A unit of the key space, see [`KeySpace::units`], has been searched completely.
+    Progress {
+        unit: usize,
+        searched: usize,
+        total: usize,
+    },
+}
+
+/// Wheel orders, reflectors and ring settings to search. Every position is searched for each of
+/// them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeySpace {
+    pub wheel_orders: Vec<WheelOrder>,
+    pub reflector_ids: Vec<&'static str>,
+    pub ring_settings: Vec<[usize; 3]>,
+}
+
+/// Result of [`solve_plugboard`].
+pub struct PlugboardSolution {
+    pub plugboard: Plugboard,
+    pub score: f64,
+    /// Scores after every accepted change, one list per climb. The first climb starts from an
+    /// empty plugboard, the others from random ones.
+    pub history: Vec<Vec<f64>>,
+}
+
+/// A running key search. Its events end once all workers have finished or were cancelled.
+pub struct KeySearch {
+    events: Receiver<SearchEvent>,
+    cancelled: Arc<AtomicBool>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+/// State shared by the workers. The key space is split into units of one machine, i.e. wheel
+/// order and reflector, and one ring setting, which the workers take in turn.
+struct SearchJob {
+    key_space: KeySpace,
+    machines: Vec<Enigma>,
+    cyphertext: String,
+    /// Every decryption is rated by all of them.
+    scorers: Vec<Arc<dyn Scorer>>,
+    threshold: f64,
+    /// Units that were already searched before a resume.
+    done: Vec<bool>,
+    next_unit: AtomicUsize,
+    searched: AtomicUsize,
+}
+
+impl Display for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} {} ring {} position {}",
+            self.reflector,
+            self.wheel_order.join("-"),
+            letters_from_positions(&self.ring_settings),
+            letters_from_positions(&self.positions)
+        ))
+    }
+}
+
+impl Key {
+    /// Parses the format of `Display`, e.g. `B I-II-III ring AAA position QRS`.
+    pub fn parse(value: &str) -> Key {
+        let items: Vec<&str> = value.split(' ').collect();
+        let [reflector, wheel_order, "ring", ring_settings, "position", positions] = items[..]
+        else {
+            panic!("invalid key {}", value);
+        };
+        let letters = |value: &str| -> [usize; 3] {
+            let positions: Vec<usize> = value.chars().map(get_position_in_alphabet).collect();
+            positions.try_into().expect("key must have three letters")
+        };
+        Key {
+            wheel_order: parse_wheel_order(wheel_order),
+            reflector: REFLECTOR_IDS
+                .iter()
+                .find(|id| **id == reflector)
+                .unwrap_or_else(|| panic!("unknown reflector {}", reflector)),
+            ring_settings: letters(ring_settings),
+            positions: letters(positions),
+        }
+    }
+}
+
+impl KeySpace {
+    /// All wheel orders and reflectors of `model`.
+    pub fn for_model(model: MachineModel, ring_settings: Vec<[usize; 3]>) -> Self {
+        KeySpace {
+            wheel_orders: model.wheel_orders(),
+            reflector_ids: model.reflector_ids().to_vec(),
+            ring_settings,
+        }
+    }
+
+    /// Ring settings of the fast and the middle rotor. The ring of the slow rotor only changes
+    /// its position and never a turnover, so it does not need to be searched.
+    pub fn all_ring_settings() -> Vec<[usize; 3]> {
+        let n = NUMBER_LETTERS_IN_ALPHABET;
+        (0..n * n).map(|i| [i % n, i / n, 0]).collect()
+    }
+
+    pub fn size(&self) -> usize {
+        self.units() * NUMBER_OF_POSITIONS
+    }
+
+    /// Number of parts the search is split into, one for every machine and ring setting.
+    pub fn units(&self) -> usize {
+        self.machines() * self.ring_settings.len()
+    }
+
+    /// The unit `key` is searched in, if it is part of the key space.
+    pub fn unit(&self, key: &Key) -> Option<usize> {
+        let wheel_order = self
+            .wheel_orders
+            .iter()
+            .position(|w| *w == key.wheel_order)?;
+        let reflector = self
+            .reflector_ids
+            .iter()
+            .position(|r| *r == key.reflector)?;
+        let rings = self
+            .ring_settings
+            .iter()
+            .position(|r| *r == key.ring_settings)?;
+        let machine = wheel_order * self.reflector_ids.len() + reflector;
+        Some(machine * self.ring_settings.len() + rings)
+    }
+
+    fn machines(&self) -> usize {
+        self.wheel_orders.len() * self.reflector_ids.len()
+    }
+
+    fn machine(&self, index: usize) -> (WheelOrder, &'static str) {
+        let reflectors = self.reflector_ids.len();
+        (
+            self.wheel_orders[index / reflectors],
+            self.reflector_ids[index % reflectors],
+        )
+    }
+}
+
+impl KeySearch {
+    pub fn events(&self) -> mpsc::Iter<'_, SearchEvent> {
+        self.events.iter()
+    }
+
+    /// Stops the workers after the key they are currently trying.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn join(self) {
+        for worker in self.workers {
+            worker.join().unwrap();
+        }
+    }
+}
+
+impl SearchJob {
+    /// The scrambler table takes 26^4 scrambles to build, without it searching a ring setting
+    /// takes one per position and letter of the cyphertext. The table is kept for one machine,
+    /// so it only pays off for machines with enough ring settings or a long cyphertext.
+    fn precompute_pays_off(&self) -> bool {
+        let rings = self.key_space.ring_settings.len();
+        rings * self.cyphertext.len() > NUMBER_LETTERS_IN_ALPHABET
+    }
+
+    fn work(&self, events: &Sender<SearchEvent>, cancelled: &AtomicBool) {
+        let total = self.key_space.size();
+        let precompute = self.precompute_pays_off();
+        // units of the same machine are adjacent, so keeping the last precomputed machine
+        // avoids most of the precomputation without holding every machine in memory
+        let mut precomputed: Option<(usize, Enigma)> = None;
+        loop {
+            let unit = self.next_unit.fetch_add(1, Ordering::Relaxed);
+            if unit >= self.key_space.units() {
+                return;
+            }
+            if self.done[unit] {
+                continue;
+            }
+            let rings = self.key_space.ring_settings.len();
+            let (machine, ring_settings) = (unit / rings, unit % rings);
+            let ring_settings = self.key_space.ring_settings[ring_settings];
+            if precomputed
+                .as_ref()
+                .is_none_or(|(index, _)| *index != machine)
+            {
+                let mut enigma = self.machines[machine].clone();
+                if precompute {
+                    enigma.precompute_scrambler();
+                }
+                precomputed = Some((machine, enigma));
+            }
+            let mut enigma = precomputed.as_ref().unwrap().1.clone();
+            enigma.set_ring_settings(ring_settings);
+            let (wheel_order, reflector) = self.key_space.machine(machine);
+            let mut attack = EnigmaAttack::new(enigma, [0, 0, 0]);
+
+            for positions in all_positions() {
+                if cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+                let text = attack.decrypt_at(positions, &self.cyphertext);
+                for (scorer, score) in self.scorers.iter().map(|s| s.score(&text)).enumerate() {
+                    if score < self.threshold {
+                        continue;
+                    }
+                    let key = Key {
+                        wheel_order,
+                        reflector,
+                        ring_settings,
+                        positions,
+                    };
+                    let candidate = ScoredKey { key, score };
+                    if events
+                        .send(SearchEvent::Candidate { scorer, candidate })
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+            }
+
+            let searched = self
+                .searched
+                .fetch_add(NUMBER_OF_POSITIONS, Ordering::Relaxed)
+                + NUMBER_OF_POSITIONS;
+            if events
+                .send(SearchEvent::Progress {
+                    unit,
+                    searched,
+                    total,
+                })
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+/// Searches the key space on `threads` threads. Keys whose decryption of `cyphertext` scores at
+/// least `threshold` with any of the `scorers` are reported as candidates, once per scorer. Each
+/// thread works on its own copy of the machines.
+pub fn spawn_key_search(
+    path: &Path,
+    key_space: KeySpace,
+    plugboard: &Plugboard,
+    cyphertext: String,
+    scorers: Vec<Arc<dyn Scorer>>,
+    threshold: f64,
+    threads: usize,
+) -> KeySearch {
+    let done = vec![false; key_space.units()];
+    start_key_search(
+        path, key_space, plugboard, cyphertext, scorers, threshold, threads, done,
+    )
+}
+
+/// Continues the search saved in `checkpoint`, skipping the units that were searched already.
+/// All parameters must be the ones the checkpoint was created with.
+#[allow(clippy::too_many_arguments)]
+pub fn resume_key_search(
+    path: &Path,
+    key_space: KeySpace,
+    plugboard: &Plugboard,
+    cyphertext: String,
+    scorer: Arc<dyn Scorer>,
+    threshold: f64,
+    threads: usize,
+    checkpoint: &Checkpoint,
+) -> KeySearch {
+    checkpoint.check_matches(&key_space, &cyphertext, scorer.as_ref(), threshold);
+    let done = checkpoint.done().to_vec();
+    start_key_search(
+        path,
+        key_space,
+        plugboard,
+        cyphertext,
+        vec![scorer],
+        threshold,
+        threads,
+        done,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_key_search(
+    path: &Path,
+    key_space: KeySpace,
+    plugboard: &Plugboard,
+    cyphertext: String,
+    scorers: Vec<Arc<dyn Scorer>>,
+    threshold: f64,
+    threads: usize,
+    done: Vec<bool>,
+) -> KeySearch {
+    let searched = done.iter().filter(|done| **done).count() * NUMBER_OF_POSITIONS;
+    let machines = (0..key_space.machines())
+        .map(|index| {
+            let (wheel_order, reflector) = key_space.machine(index);
+            let assembly = RotorAssembly::from_file(path, wheel_order, reflector);
+            Enigma::new(assembly, plugboard.clone())
+        })
+        .collect();
+    let job = Arc::new(SearchJob {
+        key_space,
+        machines,
+        cyphertext,
+        scorers,
+        threshold,
+        done,
+        next_unit: AtomicUsize::new(0),
+        searched: AtomicUsize::new(searched),
+    });
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (sender, events) = mpsc::channel();
+    let workers = (0..threads.max(1))
+        .map(|_| {
+            let job = Arc::clone(&job);
+            let sender = sender.clone();
+            let cancelled = Arc::clone(&cancelled);
+            thread::spawn(move || job.work(&sender, &cancelled))
+        })
+        .collect();
+    KeySearch {
+        events,
+        cancelled,
+        workers,
+    }
 }
 
-pub fn known_plaintext_attack(message: &Message, known_plaintext: String) {
-    let possible_positions = find_possible_positions(message, &known_plaintext);
-    print_possible_positions(message, &known_plaintext, &possible_positions);
-    println!("possible positions: {}", possible_positions.len());
-    let first_position = possible_positions.get(0).unwrap();
-    brute_force_plugboard(message, known_plaintext.as_str(), *first_position);
+/// Recovers the ring settings of the fast and the middle rotor once wheel order and positions
+/// are known, e.g. from a search with ring settings `AAA`. Changing a ring setting together with
+/// the position keeps the wiring offset, so the decryption only changes where a turnover now
+/// happens earlier or later. Returns all keys with the best score of `scorer`.
+pub fn recover_ring_settings(
+    path: &Path,
+    key: Key,
+    plugboard: &Plugboard,
+    cyphertext: &str,
+    scorer: &dyn Scorer,
+) -> Vec<ScoredKey> {
+    let n = NUMBER_LETTERS_IN_ALPHABET;
+    let assembly = RotorAssembly::from_file(path, key.wheel_order, key.reflector);
+    let mut enigma = Enigma::new(assembly, plugboard.clone());
+    enigma.precompute_scrambler();
+    let offsets: [usize; 3] =
+        std::array::from_fn(|i| (key.positions[i] + n - key.ring_settings[i]) % n);
+
+    let mut best: Vec<ScoredKey> = Vec::new();
+    for ring_settings in KeySpace::all_ring_settings() {
+        let ring_settings = [ring_settings[0], ring_settings[1], key.ring_settings[2]];
+        let positions = std::array::from_fn(|i| (offsets[i] + ring_settings[i]) % n);
+        enigma.set_ring_settings(ring_settings);
+        let mut attack = EnigmaAttack::new(enigma.clone(), positions);
+        let score = attack.score_at(positions, cyphertext, scorer);
+        let candidate = ScoredKey {
+            key: Key {
+                ring_settings,
+                positions,
+                ..key
+            },
+            score,
+        };
+        match best.first() {
+            Some(first) if score < first.score => {}
+            Some(first) if score == first.score => best.push(candidate),
+            _ => best = vec![candidate],
+        }
+    }
+    best
 }
 
-fn brute_force_plugboard(message: &Message, known_plaintext: &str, position: usize) {
-    let mut attack = EnigmaAttack::new_default();
-    for (crypt_char, clear_char) in
-        zip(message.text.chars().skip(position), known_plaintext.chars())
-    {
-        println!("{} {}", crypt_char, clear_char);
+/// Finds the plugboard for fixed rotor settings by hill climbing. Pairs are added, removed and
+/// swapped as long as that improves the score of `scorer`, then the climb is restarted
+/// `restarts` times from a random plugboard.
+pub fn solve_plugboard(
+    enigma: Enigma,
+    positions: [usize; 3],
+    cyphertext: &str,
+    scorer: &dyn Scorer,
+    restarts: usize,
+    random: &mut Random,
+) -> PlugboardSolution {
+    let mut attack = EnigmaAttack::new(enigma, positions);
+    let mut score_of = |plugboard: &Plugboard| {
+        attack.set_plugboard(plugboard);
+        attack.score_at(positions, cyphertext, scorer)
+    };
+
+    let mut best: Option<PlugboardSolution> = None;
+    let mut history = Vec::new();
+    for climb in 0..=restarts {
+        let mut plugboard = if climb == 0 {
+            Plugboard::new(vec![])
+        } else {
+            random_plugboard(random)
+        };
+        let mut score = score_of(&plugboard);
+        let mut scores = vec![score];
+        'climb: loop {
+            let mut neighbours = plugboard_neighbours(&plugboard);
+            random.shuffle(&mut neighbours);
+            for neighbour in neighbours {
+                let neighbour_score = score_of(&neighbour);
+                if neighbour_score > score {
+                    plugboard = neighbour;
+                    score = neighbour_score;
+                    scores.push(score);
+                    continue 'climb;
+                }
+            }
+            break;
+        }
+        history.push(scores);
+        if best.as_ref().is_none_or(|best| score > best.score) {
+            best = Some(PlugboardSolution {
+                plugboard,
+                score,
+                history: Vec::new(),
+            });
+        }
     }
-    attack.reset_positions();
+    let mut best = best.unwrap();
+    best.history = history;
+    best
+}
+
+fn random_plugboard(random: &mut Random) -> Plugboard {
+    let mut letters = ALPHABET;
+    random.shuffle(&mut letters);
+    let pairs = random.below(MAX_PAIRS + 1);
+    let pairs = letters[..2 * pairs]
+        .chunks(2)
+        .map(|chars| Pair::new(chars[0], chars[1]))
+        .collect();
+    Plugboard::new(pairs)
 }
 
-fn find_possible_positions(message: &Message, known_plaintext: &str) -> Vec<usize> {
-    let plaintext_length = known_plaintext.len();
-    let max_index = message.text.len() - plaintext_length;
+/// All plugboards that differ by one added, removed or swapped pair.
+fn plugboard_neighbours(plugboard: &Plugboard) -> Vec<Plugboard> {
+    let unplugged: Vec<char> = ALPHABET
+        .into_iter()
+        .filter(|letter| !plugboard.is_plugged(*letter))
+        .collect();
+    let pairs = plugboard.pairs();
     let mut result = Vec::new();
-    for i in 0..max_index {
-        if characters_at_each_position_do_not_match(
-            &message.text[i..i + plaintext_length],
-            known_plaintext,
-        ) {
-            result.push(i);
+
+    if pairs.len() < MAX_PAIRS {
+        for (i, a) in unplugged.iter().enumerate() {
+            for b in &unplugged[i + 1..] {
+                let mut neighbour = plugboard.clone();
+                neighbour.add_pair(Pair::new(*a, *b));
+                result.push(neighbour);
+            }
+        }
+    }
+    for (i, pair) in pairs.iter().enumerate() {
+        let mut removed = plugboard.clone();
+        removed.remove_pair(i);
+        result.push(removed.clone());
+
+        // replace one letter of the pair by an unplugged letter
+        for kept in pair.chars() {
+            for other in &unplugged {
+                let mut neighbour = removed.clone();
+                neighbour.add_pair(Pair::new(kept, *other));
+                result.push(neighbour);
+            }
+        }
+        // exchange letters with another pair
+        for (j, other) in pairs.iter().enumerate().skip(i + 1) {
+            let [a, b] = pair.chars();
+            let [c, d] = other.chars();
+            for (first, second) in [
+                (Pair::new(a, c), Pair::new(b, d)),
+                (Pair::new(a, d), Pair::new(b, c)),
+            ] {
+                let mut neighbour = removed.clone();
+                neighbour.remove_pair(j - 1);
+                neighbour.add_pair(first);
+                neighbour.add_pair(second);
+                result.push(neighbour);
+            }
         }
     }
     result
 }
 
-fn print_possible_positions(message: &Message, known_plaintext: &str, result: &[usize]) {
-    for (i, index) in result.iter().enumerate() {
-        if i % 10 == 0 {
-            println!("{}", message.text);
-        }
-        for _ in 0..*index {
-            print!(" ");
+/// Finds the placements of the crib, see [`Crib`]. For the spellings at the first few offsets,
+/// the key space is searched for the keys that match the crib best without plugboard, and the
+/// plugboard is solved for each of those keys.
+pub fn known_plaintext_attack(
+    message: &Message,
+    known_plaintext: String,
+    key_space: &KeySpace,
+) -> AttackReport {
+    let mut timings = Vec::new();
+    let start = Instant::now();
+    let placements = Crib::parse(&known_plaintext).placements(&message.text);
+    let mut crib_offsets: Vec<usize> = placements
+        .iter()
+        .map(|placement| placement.offset)
+        .collect();
+    crib_offsets.dedup();
+    timings.push(Timing {
+        stage: "find crib offsets",
+        duration: start.elapsed(),
+    });
+
+    let start = Instant::now();
+    let solved_offsets = &crib_offsets[..crib_offsets.len().min(SOLVED_CRIB_OFFSETS)];
+    let solved: Vec<&Placement> = placements
+        .iter()
+        .filter(|placement| solved_offsets.contains(&placement.offset))
+        .collect();
+    let keys: Vec<(&Placement, Key)> = best_keys(message, &solved, key_space)
+        .into_iter()
+        .zip(&solved)
+        .flat_map(|(keys, placement)| keys.into_iter().map(move |key| (*placement, key)))
+        .collect();
+    timings.push(Timing {
+        stage: "search keys",
+        duration: start.elapsed(),
+    });
+
+    let start = Instant::now();
+    let mut candidates: Vec<KeyCandidate> = keys
+        .into_iter()
+        .map(|(placement, key)| brute_force_plugboard(message, placement, key))
+        .collect();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    timings.push(Timing {
+        stage: "solve plugboard",
+        duration: start.elapsed(),
+    });
+
+    AttackReport {
+        crib: known_plaintext,
+        crib_offsets,
+        candidates,
+        timings,
+    }
+}
+
+/// For every placement, the keys of `key_space` that decrypt most letters of the placed crib
+/// without plugboard. All placements are scored in one search of the key space.
+fn best_keys(message: &Message, placements: &[&Placement], key_space: &KeySpace) -> Vec<Vec<Key>> {
+    let Some(end) = placements
+        .iter()
+        .map(|placement| placement.offset + placement.variant.len())
+        .max()
+    else {
+        return Vec::new();
+    };
+    let scorers: Vec<Arc<dyn Scorer>> = placements
+        .iter()
+        .map(|placement| {
+            Arc::new(CribScorer::new(placement.variant.clone(), placement.offset))
+                as Arc<dyn Scorer>
+        })
+        .collect();
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let search = spawn_key_search(
+        Path::new(rotor::PATH),
+        key_space.clone(),
+        &Plugboard::new(vec![]),
+        message.text[..end].to_string(),
+        scorers,
+        KEY_THRESHOLD,
+        threads,
+    );
+    let mut candidates: Vec<Vec<ScoredKey>> = vec![Vec::new(); placements.len()];
+    for event in search.events() {
+        if let SearchEvent::Candidate { scorer, candidate } = event {
+            candidates[scorer].push(candidate);
         }
-        println!("{}", known_plaintext);
     }
+    search.join();
+    candidates
+        .into_iter()
+        .map(|mut candidates| {
+            candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+            candidates
+                .into_iter()
+                .take(KEYS_PER_PLACEMENT)
+                .map(|candidate| candidate.key)
+                .collect()
+        })
+        .collect()
 }
 
-fn characters_at_each_position_do_not_match(text_1: &str, text_2: &str) -> bool {
-    assert_eq!(text_1.len(), text_2.len());
-    assert_ne!(text_1.len(), 0);
-    zip(text_1.chars(), text_2.chars()).all(|(x, y)| x != y)
+fn brute_force_plugboard(message: &Message, placement: &Placement, key: Key) -> KeyCandidate {
+    let assembly = RotorAssembly::from_file(Path::new(rotor::PATH), key.wheel_order, key.reflector);
+    let mut enigma = Enigma::new(assembly, Plugboard::new(vec![]));
+    enigma.set_ring_settings(key.ring_settings);
+    let end = placement.offset + placement.variant.len();
+    let scorer = CribScorer::new(placement.variant.clone(), placement.offset);
+    let solution = solve_plugboard(
+        enigma.clone(),
+        key.positions,
+        &message.text[..end],
+        &scorer,
+        PLUGBOARD_RESTARTS,
+        &mut Random::new(PLUGBOARD_SEED),
+    );
+    let mut attack = EnigmaAttack::new(enigma, key.positions);
+    attack.set_plugboard(&solution.plugboard);
+    let preview: String = message.text.chars().take(PREVIEW_LENGTH).collect();
+    KeyCandidate {
+        crib_offset: placement.offset,
+        key,
+        plugboard: solution.plugboard,
+        score: solution.score,
+        preview: attack.decrypt_at(key.positions, &preview),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::cryptoattack::characters_at_each_position_do_not_match;
+    use crate::cryptoattack::*;
+    use crate::message::Indicator;
+
+    const PLAINTEXT: &str = "KEINEBESONDERENEREIGNISSE";
+
+    fn search(key: Key, key_space: KeySpace) -> KeySearch {
+        let mut enigma = Enigma::new(
+            RotorAssembly::from_file(Path::new(rotor::PATH), key.wheel_order, key.reflector),
+            Plugboard::new(vec![]),
+        );
+        enigma.set_ring_settings(key.ring_settings);
+        enigma.set_positions(key.positions);
+        let cyphertext = enigma.encode_message(PLAINTEXT);
+        let scorer = Arc::new(CribScorer::new(PLAINTEXT[0..10].to_string(), 0));
+        spawn_key_search(
+            Path::new(rotor::PATH),
+            key_space,
+            &Plugboard::new(vec![]),
+            cyphertext,
+            vec![scorer],
+            1.0,
+            2,
+        )
+    }
+
+    #[test]
+    fn key_search_finds_key() {
+        let key = Key {
+            wheel_order: ["II", "I", "III"],
+            reflector: "C",
+            ring_settings: [0, 3, 0],
+            positions: [5, 17, 9],
+        };
+        let key_space = KeySpace {
+            wheel_orders: vec![["II", "I", "III"]],
+            reflector_ids: vec!["B", "C"],
+            ring_settings: vec![[0, 0, 0], [0, 3, 0]],
+        };
+        let total = key_space.size();
+        let search = search(key, key_space);
+        let mut candidates = Vec::new();
+        let mut searched = 0;
+        for event in search.events() {
+            match event {
+                SearchEvent::Candidate { candidate, .. } => candidates.push(candidate.key),
+                SearchEvent::Progress { searched: s, .. } => searched = searched.max(s),
+            }
+        }
+        search.join();
+        assert_eq!(searched, total);
+        assert!(candidates.contains(&key));
+    }
 
     #[test]
-    fn test_characters_at_each_position_do_not_match() {
-        assert!(characters_at_each_position_do_not_match("A", "B"));
-        assert!(characters_at_each_position_do_not_match("A", "Z"));
-        assert!(characters_at_each_position_do_not_match("ABC", "DEF"));
-        assert!(!characters_at_each_position_do_not_match("A", "A"));
-        assert!(characters_at_each_position_do_not_match("ABCDE", "ESKXL"));
-        assert!(characters_at_each_position_do_not_match("ABCDE", "BCDEA"));
+    fn key_search_reports_the_scorer_of_a_candidate() {
+        let key = Key::parse("B I-II-III ring AAA position GKA");
+        let mut enigma = Enigma::new(RotorAssembly::new_default(), Plugboard::new(vec![]));
+        enigma.set_positions(key.positions);
+        let cyphertext = enigma.encode_message(PLAINTEXT);
+        let scorers: Vec<Arc<dyn Scorer>> = vec![
+            Arc::new(CribScorer::new("SONDERE".to_string(), 8)),
+            Arc::new(CribScorer::new("BESONDE".to_string(), 5)),
+        ];
+        let key_space = KeySpace {
+            wheel_orders: vec![key.wheel_order],
+            reflector_ids: vec![key.reflector],
+            ring_settings: vec![key.ring_settings],
+        };
+        let search = spawn_key_search(
+            Path::new(rotor::PATH),
+            key_space,
+            &Plugboard::new(vec![]),
+            cyphertext,
+            scorers,
+            1.0,
+            1,
+        );
+        let mut candidates = Vec::new();
+        for event in search.events() {
+            if let SearchEvent::Candidate { scorer, candidate } = event {
+                candidates.push((scorer, candidate.key));
+            }
+        }
+        search.join();
+        assert!(candidates.contains(&(1, key)));
+        assert!(!candidates.contains(&(0, key)));
     }
 
     #[test]
-    #[should_panic]
-    fn test_characters_at_each_position_do_not_match_unequal_lengths_1() {
-        assert!(!characters_at_each_position_do_not_match("ABCDE", "ABCD"));
+    fn key_search_can_be_cancelled() {
+        let key = Key {
+            wheel_order: ["I", "II", "III"],
+            reflector: "B",
+            ring_settings: [0, 0, 0],
+            positions: [0, 0, 0],
+        };
+        // small enough to finish without cancelling, so that a broken cancel fails the test
+        let key_space = KeySpace {
+            wheel_orders: vec![key.wheel_order],
+            reflector_ids: vec![key.reflector],
+            ring_settings: KeySpace::all_ring_settings()[..64].to_vec(),
+        };
+        let total = key_space.size();
+        let search = search(key, key_space);
+        let mut searched = 0;
+        for event in search.events() {
+            if let SearchEvent::Progress { searched: s, .. } = event {
+                // the workers stop within the units they are working on
+                searched = searched.max(s);
+                search.cancel();
+            }
+        }
+        search.join();
+        assert!(searched > 0);
+        assert!(
+            searched <= 4 * NUMBER_OF_POSITIONS,
+            "searched {} of {}",
+            searched,
+            total
+        );
+    }
+
+    #[test]
+    fn key_search_resumes_from_checkpoint() {
+        let key = Key {
+            wheel_order: ["III", "II", "I"],
+            reflector: "B",
+            ring_settings: [0, 0, 0],
+            positions: [8, 1, 24],
+        };
+        let key_space = KeySpace {
+            wheel_orders: vec![["I", "II", "III"], ["III", "II", "I"]],
+            reflector_ids: vec!["B"],
+            ring_settings: vec![[0, 0, 0]],
+        };
+        let mut enigma = Enigma::new(
+            RotorAssembly::from_file(Path::new(rotor::PATH), key.wheel_order, key.reflector),
+            Plugboard::new(vec![]),
+        );
+        enigma.set_positions(key.positions);
+        let cyphertext = enigma.encode_message(PLAINTEXT);
+        let scorer = Arc::new(CribScorer::new(PLAINTEXT.to_string(), 0));
+        let mut checkpoint = Checkpoint::new(&key_space, &cyphertext, scorer.as_ref(), 1.0);
+        checkpoint.record(&SearchEvent::Progress {
+            unit: 0,
+            searched: NUMBER_OF_POSITIONS,
+            total: key_space.size(),
+        });
+
+        let search = resume_key_search(
+            Path::new(rotor::PATH),
+            key_space,
+            &Plugboard::new(vec![]),
+            cyphertext,
+            scorer,
+            1.0,
+            2,
+            &checkpoint,
+        );
+        for event in search.events() {
+            if let SearchEvent::Progress { unit, .. } = event {
+                assert_eq!(unit, 1);
+            }
+            checkpoint.record(&event);
+        }
+        search.join();
+        assert!(checkpoint.is_complete());
+        assert_eq!(checkpoint.candidates[0].key, key);
     }
 
     #[test]
-    #[should_panic]
-    fn test_characters_at_each_position_do_not_match_unequal_lengths_2() {
-        assert!(!characters_at_each_position_do_not_match("ABCDE", "ABCDEF"));
+    fn parse_key() {
+        let key = Key {
+            wheel_order: ["VI", "I", "III"],
+            reflector: "C",
+            ring_settings: [0, 25, 1],
+            positions: [16, 17, 18],
+        };
+        assert_eq!(key.to_string(), "C VI-I-III ring AZB position QRS");
+        assert_eq!(Key::parse(&key.to_string()), key);
+    }
+
+    #[test]
+    fn recovers_ring_settings() {
+        let key = Key {
+            wheel_order: ["III", "I", "II"],
+            reflector: "B",
+            ring_settings: [11, 4, 0],
+            positions: [20, 7, 2],
+        };
+        let path = Path::new(rotor::PATH);
+        let mut enigma = Enigma::new(
+            RotorAssembly::from_file(path, key.wheel_order, key.reflector),
+            Plugboard::new(vec![]),
+        );
+        enigma.set_ring_settings(key.ring_settings);
+        enigma.set_positions(key.positions);
+        let plaintext = PLAINTEXT.repeat(30);
+        let cyphertext = enigma.encode_message(&plaintext);
+
+        // positions as found by a search without ring settings
+        let partial = Key {
+            ring_settings: [0, 0, 0],
+            positions: [9, 3, 2],
+            ..key
+        };
+        let scorer = CribScorer::new(plaintext, 0);
+        let best =
+            recover_ring_settings(path, partial, &Plugboard::new(vec![]), &cyphertext, &scorer);
+        assert!(best.iter().all(|candidate| candidate.score == 1.0));
+        assert!(best.iter().any(|candidate| candidate.key == key));
+    }
+
+    #[test]
+    fn known_plaintext_attack_reports_wheel_order_and_reflector() {
+        let key = Key {
+            wheel_order: ["IV", "II", "V"],
+            reflector: "C",
+            ring_settings: [0, 0, 0],
+            positions: [4, 22, 13],
+        };
+        let mut enigma = Enigma::new(
+            RotorAssembly::from_file(Path::new(rotor::PATH), key.wheel_order, key.reflector),
+            Plugboard::new(vec![Pair::new('E', 'Q')]),
+        );
+        enigma.set_positions(key.positions);
+        // as long as the crib, so there is only one crib offset to search
+        let cyphertext = enigma.encode_message(PLAINTEXT);
+        let message = Message::new(Indicator::new("AAA".to_string()), cyphertext);
+        let key_space = KeySpace {
+            wheel_orders: vec![["II", "IV", "V"], ["IV", "II", "V"]],
+            reflector_ids: vec!["B", "C"],
+            ring_settings: vec![[0, 0, 0]],
+        };
+
+        let report = known_plaintext_attack(&message, PLAINTEXT.to_string(), &key_space);
+        let best = &report.candidates[0];
+        assert_eq!(best.crib_offset, 0);
+        assert_eq!(best.key, key);
+        assert_eq!(best.score, 1.0);
+        assert!(best.preview.starts_with("KEINEBESONDERENEREIGNISSE"));
+    }
+
+    /// Enciphers `plaintext` with `key` and a single plug, and attacks it on a key space of only
+    /// the key's machine.
+    fn attack_with_crib(key: Key, plaintext: &str, crib: &str) -> AttackReport {
+        let mut enigma = Enigma::new(
+            RotorAssembly::from_file(Path::new(rotor::PATH), key.wheel_order, key.reflector),
+            Plugboard::new(vec![Pair::new('E', 'Q')]),
+        );
+        enigma.set_positions(key.positions);
+        let cyphertext = enigma.encode_message(plaintext);
+        let message = Message::new(Indicator::new("AAA".to_string()), cyphertext);
+        let key_space = KeySpace {
+            wheel_orders: vec![key.wheel_order],
+            reflector_ids: vec![key.reflector],
+            ring_settings: vec![key.ring_settings],
+        };
+        known_plaintext_attack(&message, crib.to_string(), &key_space)
+    }
+
+    #[test]
+    fn known_plaintext_attack_with_anchored_crib() {
+        let key = Key::parse("B I-II-III ring AAA position DMU");
+        let report = attack_with_crib(key, &format!("ANX{}", PLAINTEXT), "^ANXKEINEBESONDERE");
+        assert_eq!(report.crib_offsets, vec![0]);
+        let best = &report.candidates[0];
+        assert_eq!(best.key, key);
+        assert_eq!(best.score, 1.0);
+    }
+
+    #[test]
+    fn known_plaintext_attack_with_digits_in_crib() {
+        let key = Key::parse("B I-II-III ring AAA position KQB");
+        let plaintext = format!("{}WETTERNULLSEQSNULLNULL", PLAINTEXT);
+        let report = attack_with_crib(key, &plaintext, "WETTER0600$");
+        let best = &report.candidates[0];
+        assert_eq!(best.crib_offset, PLAINTEXT.len());
+        assert_eq!(best.key, key);
+        assert_eq!(best.score, 1.0);
+    }
+
+    #[test]
+    fn known_plaintext_attack_with_crib_longer_than_message() {
+        let message = Message::new(Indicator::new("AAA".to_string()), "XYZ".to_string());
+        let key_space = KeySpace::for_model(MachineModel::EnigmaI, vec![[0, 0, 0]]);
+        let report = known_plaintext_attack(&message, PLAINTEXT.to_string(), &key_space);
+        assert!(report.crib_offsets.is_empty());
+        assert!(report.candidates.is_empty());
+    }
+
+    #[test]
+    fn solves_plugboard() {
+        let plugboard = Plugboard::new(vec![
+            Pair::new('A', 'S'),
+            Pair::new('Q', 'M'),
+            Pair::new('K', 'P'),
+            Pair::new('R', 'V'),
+            Pair::new('H', 'L'),
+        ]);
+        let mut enigma = Enigma::new(RotorAssembly::new_default(), plugboard);
+        enigma.precompute_scrambler();
+        let positions = [3, 12, 20];
+        enigma.set_positions(positions);
+        let plaintext = PLAINTEXT.repeat(4);
+        let cyphertext = enigma.encode_message(&plaintext);
+
+        enigma.set_plugboard(&Plugboard::new(vec![]));
+        let scorer = CribScorer::new(plaintext.clone(), 0);
+        let mut random = Random::new(3);
+        let solution = solve_plugboard(
+            enigma.clone(),
+            positions,
+            &cyphertext,
+            &scorer,
+            2,
+            &mut random,
+        );
+        assert_eq!(solution.score, 1.0);
+        assert_eq!(solution.history.len(), 3);
+        assert!(solution.history[0].windows(2).all(|w| w[0] < w[1]));
+
+        enigma.set_plugboard(&solution.plugboard);
+        enigma.set_positions(positions);
+        assert_eq!(enigma.encode_message(&cyphertext), plaintext);
     }
 
     #[test]
-    #[should_panic]
-    fn test_characters_at_each_position_do_not_match_length_zero() {
-        assert!(!characters_at_each_position_do_not_match("", ""));
+    fn plugboard_neighbours_are_valid() {
+        let plugboard = Plugboard::new(vec![Pair::new('A', 'B'), Pair::new('C', 'D')]);
+        let neighbours = plugboard_neighbours(&plugboard);
+        // 22 unplugged letters: adding 231, removing 2, swapping 2 * 2 * 22, exchanging 2
+        assert_eq!(neighbours.len(), 231 + 2 + 88 + 2);
+        assert!(neighbours.iter().all(|n| n.pairs().len() <= 3));
     }
 }
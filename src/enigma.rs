@@ -1,45 +1,93 @@
-use crate::alphabet::{get_position_in_alphabet, is_capital_letter};
-use crate::message::{Indicator, Message};
-use crate::mode::Mode;
+use crate::alphabet::{
+    from_index, get_position_in_alphabet, is_capital_letter, is_combining_mark, normalise, to_index,
+};
+use crate::message::{ungroup, Indicator, Message};
+use crate::mode::{Mode, Procedure};
 use crate::plugboard::Plugboard;
+use crate::rotor::Table;
 use crate::rotorassembly::RotorAssembly;
 
+#[derive(Clone)]
 pub struct Enigma {
     assembly: RotorAssembly,
-    plugboard: Plugboard,
+    plugboard: Table,
 }
 
 impl Enigma {
     pub fn decrypt(&mut self, message: Message) -> Message {
-        let decrypted_indicator = self.encode_indicator(&message.indicator, Mode::Decrypt);
-        self.set_indicator(&decrypted_indicator);
-        let text = self.encode_message(&message.text);
-        Message::new(decrypted_indicator, text)
+        self.decrypt_with(message, Procedure::DoubledKey)
     }
 
     pub fn encrypt(&mut self, message: Message) -> Message {
-        let encrypted_indicator = self.encode_indicator(&message.indicator, Mode::Encrypt);
-        self.set_indicator(&message.indicator);
+        self.encrypt_with(message, Procedure::DoubledKey)
+    }
+
+    /// The text may be written in groups.
+    pub fn decrypt_with(&mut self, message: Message, procedure: Procedure) -> Message {
+        let decrypted_indicator =
+            self.encode_indicator(&message.indicator, Mode::Decrypt, procedure);
+        self.set_indicator(&decrypted_indicator, procedure);
+        let text = self.encode_message(&ungroup(&message.text));
+        Message::new(decrypted_indicator, text)
+    }
+
+    pub fn encrypt_with(&mut self, message: Message, procedure: Procedure) -> Message {
+        let encrypted_indicator =
+            self.encode_indicator(&message.indicator, Mode::Encrypt, procedure);
+        self.set_indicator(&message.indicator, procedure);
         let text = self.encode_message(&message.text);
         Message::new(encrypted_indicator, text)
     }
 
-    fn set_indicator(&mut self, indicator: &Indicator) {
-        let mut positions = [0; 3];
-        for (i, char) in indicator.get_first_triplet().chars().enumerate() {
-            positions[i] = get_position_in_alphabet(char);
-        }
-        self.set_positions(positions);
+    /// Like [`Enigma::decrypt_with`], but see [`Enigma::encode_preserving`].
+    pub fn decrypt_preserving(&mut self, message: Message, procedure: Procedure) -> Message {
+        let decrypted_indicator =
+            self.encode_indicator(&message.indicator, Mode::Decrypt, procedure);
+        self.set_indicator(&decrypted_indicator, procedure);
+        let text = self.encode_preserving(&message.text);
+        Message::new(decrypted_indicator, text)
     }
 
-    fn encode_indicator(&mut self, indicator: &Indicator, mode: Mode) -> Indicator {
-        indicator.sanity_check(&mode);
-        match mode {
-            Mode::Decrypt => self.decrypt_indicator(indicator),
-            Mode::Encrypt => self.encrypt_indicator(indicator),
+    /// Like [`Enigma::encrypt_with`], but see [`Enigma::encode_preserving`].
+    pub fn encrypt_preserving(&mut self, message: Message, procedure: Procedure) -> Message {
+        let encrypted_indicator =
+            self.encode_indicator(&message.indicator, Mode::Encrypt, procedure);
+        self.set_indicator(&message.indicator, procedure);
+        let text = self.encode_preserving(&message.text);
+        Message::new(encrypted_indicator, text)
+    }
+
+    /// Sets the rotors to the message key of a plain indicator.
+    fn set_indicator(&mut self, indicator: &Indicator, procedure: Procedure) {
+        let key = match procedure {
+            Procedure::DoubledKey => indicator.get_first_triplet(),
+            Procedure::SingleKey => indicator.get_second_triplet(),
+        };
+        self.set_positions(triplet_to_positions(key));
+    }
+
+    fn encode_indicator(
+        &mut self,
+        indicator: &Indicator,
+        mode: Mode,
+        procedure: Procedure,
+    ) -> Indicator {
+        indicator.sanity_check_for(&mode, procedure);
+        match (procedure, mode) {
+            (Procedure::DoubledKey, Mode::Decrypt) => self.decrypt_indicator(indicator),
+            (Procedure::DoubledKey, Mode::Encrypt) => self.encrypt_indicator(indicator),
+            (Procedure::SingleKey, _) => self.encode_single_key_indicator(indicator),
         }
     }
 
+    /// Enciphers or deciphers the message key at the start position sent in clear.
+    fn encode_single_key_indicator(&mut self, indicator: &Indicator) -> Indicator {
+        let start = indicator.get_first_triplet();
+        self.set_positions(triplet_to_positions(start));
+        let key = self.encode_message(indicator.get_second_triplet());
+        Indicator::new(format!("{}{}", start, key))
+    }
+
     fn encrypt_indicator(&mut self, indicator: &Indicator) -> Indicator {
         let mut result = self.encode_message(indicator.get_first_triplet());
         let second_triplet = self.encode_message(indicator.get_first_triplet());
@@ -61,16 +109,34 @@ impl Enigma {
         self.assembly.set_positions(positions);
     }
 
+    pub fn set_ring_settings(&mut self, ring_settings: [usize; 3]) {
+        self.assembly.set_ring_settings(ring_settings);
+    }
+
+    pub fn set_plugboard(&mut self, plugboard: &Plugboard) {
+        self.plugboard = plugboard.table();
+    }
+
     pub fn new(assembly: RotorAssembly, plugboard: Plugboard) -> Self {
         Enigma {
             assembly,
-            plugboard,
+            plugboard: plugboard.table(),
         }
     }
 
+    /// See [`RotorAssembly::precompute_scrambler`].
+    pub fn precompute_scrambler(&mut self) {
+        self.assembly.precompute_scrambler();
+    }
+
     fn encode_char(&mut self, input: char) -> char {
-        self.plugboard
-            .encode_char(self.assembly.encode_char(self.plugboard.encode_char(input)))
+        from_index(self.encode_index(to_index(input)))
+    }
+
+    fn encode_index(&mut self, input: u8) -> u8 {
+        let plugged = self.plugboard[input as usize];
+        let scrambled = self.assembly.encode_index(plugged);
+        self.plugboard[scrambled as usize]
     }
 
     pub fn encode_message(&mut self, input: &str) -> String {
@@ -79,6 +145,42 @@ impl Enigma {
             .map(|c| self.encode_char(sanity_check(c)))
             .collect::<String>()
     }
+
+    /// Enciphers the letters [`normalise`] finds, keeping their case, and copies everything else
+    /// to the output without stepping the rotors. Combining marks of a letter are dropped with
+    /// it, they would give away the plain letter.
+    pub fn encode_preserving(&mut self, input: &str) -> String {
+        let mut result = String::new();
+        let mut after_letter = false;
+        for c in input.chars() {
+            if after_letter && is_combining_mark(c) {
+                continue;
+            }
+            let Some(letters) = normalise(c) else {
+                result.push(c);
+                after_letter = false;
+                continue;
+            };
+            after_letter = true;
+            for letter in letters.chars() {
+                let encoded = self.encode_char(letter);
+                if c.is_lowercase() {
+                    result.push(encoded.to_ascii_lowercase());
+                } else {
+                    result.push(encoded);
+                }
+            }
+        }
+        result
+    }
+}
+
+fn triplet_to_positions(triplet: &str) -> [usize; 3] {
+    let mut positions = [0; 3];
+    for (i, char) in triplet.chars().enumerate() {
+        positions[i] = get_position_in_alphabet(char);
+    }
+    positions
 }
 
 fn sanity_check(input: char) -> char {
@@ -91,6 +193,8 @@ fn sanity_check(input: char) -> char {
 
 #[cfg(test)]
 mod tests {
+    use crate::message::{Indicator, Message, GROUPS};
+    use crate::mode::Procedure;
     use crate::rotorassembly::RotorAssembly;
     use crate::{enigma, plugboard, Enigma, Plugboard};
     use std::path::Path;
@@ -100,10 +204,7 @@ mod tests {
     fn new_default() -> Enigma {
         let assembly = RotorAssembly::new_default();
         let plugboard = Plugboard::from_file(Path::new(plugboard::PATH));
-        Enigma {
-            assembly,
-            plugboard,
-        }
+        Enigma::new(assembly, plugboard)
     }
 
     #[test]
@@ -132,6 +233,7 @@ mod tests {
 
     #[ignore]
     #[test]
+    #[allow(unreachable_code)]
     fn can_encrypt_and_decrypt_message_with_random_settings() {
         todo!();
 
@@ -144,6 +246,102 @@ mod tests {
         assert_eq!(MESSAGE.to_string(), output);
     }
 
+    #[test]
+    fn doubled_key_procedure() {
+        let message = Message::new(Indicator::new("QRS".to_string()), MESSAGE.to_string());
+        let mut enigma = new_default();
+        enigma.set_positions([3, 8, 1]);
+        let encrypted = enigma.encrypt(message);
+        let indicator = format!(
+            "{}{}",
+            encrypted.indicator.get_first_triplet(),
+            encrypted.indicator.get_second_triplet()
+        );
+        // the doubled key QRSQRS enciphered at DIB
+        assert_eq!(indicator, "UVRFXB");
+
+        let mut enigma = new_default();
+        enigma.set_positions([3, 8, 1]);
+        let decrypted = enigma.decrypt(encrypted);
+        assert_eq!(decrypted.indicator.get_first_triplet(), "QRS");
+        assert_eq!(decrypted.text, MESSAGE);
+    }
+
+    #[test]
+    fn decrypt_grouped_text() {
+        let message = Message::new(Indicator::new("QRS".to_string()), MESSAGE.to_string());
+        let encrypted = new_default().encrypt(message);
+        let grouped = Message::new(encrypted.indicator, GROUPS.format(&encrypted.text));
+        assert!(grouped.text.contains(' '));
+        let decrypted = new_default().decrypt(grouped);
+        assert_eq!(decrypted.text, MESSAGE);
+    }
+
+    #[test]
+    fn single_key_procedure() {
+        // start position WZA sent in clear, message key SXT
+        let message = Message::new(Indicator::new("WZASXT".to_string()), MESSAGE.to_string());
+        let encrypted = new_default().encrypt_with(message, Procedure::SingleKey);
+        let mut enigma = new_default();
+        enigma.set_positions([18, 23, 19]);
+        assert_eq!(encrypted.text, enigma.encode_message(MESSAGE));
+        assert_eq!(encrypted.indicator.get_first_triplet(), "WZA");
+        assert_ne!(encrypted.indicator.get_second_triplet(), "SXT");
+
+        // the Grundstellung of the day does not matter
+        let mut enigma = new_default();
+        enigma.set_positions([11, 4, 20]);
+        let decrypted = enigma.decrypt_with(encrypted, Procedure::SingleKey);
+        assert_eq!(decrypted.indicator.get_second_triplet(), "SXT");
+        assert_eq!(decrypted.text, MESSAGE);
+    }
+
+    #[test]
+    #[should_panic]
+    fn single_key_indicator_needs_start_position() {
+        let message = Message::new(Indicator::new("SXT".to_string()), MESSAGE.to_string());
+        new_default().encrypt_with(message, Procedure::SingleKey);
+    }
+
+    #[test]
+    fn preserves_other_characters() {
+        let encoded = new_default().encode_preserving("Dies ist, 1 Test!");
+        let letters: String = encoded.chars().filter(char::is_ascii_alphabetic).collect();
+        assert_eq!(
+            letters.to_uppercase(),
+            new_default().encode_message("DIESISTTEST")
+        );
+        let others: Vec<(usize, char)> = encoded
+            .chars()
+            .enumerate()
+            .filter(|(_, c)| !c.is_ascii_alphabetic())
+            .collect();
+        assert_eq!(
+            others,
+            vec![
+                (4, ' '),
+                (8, ','),
+                (9, ' '),
+                (10, '1'),
+                (11, ' '),
+                (16, '!')
+            ]
+        );
+        assert!(encoded.starts_with(|c: char| c.is_ascii_uppercase()));
+        assert_eq!(
+            new_default().encode_preserving(&encoded),
+            "Dies ist, 1 Test!"
+        );
+        assert_eq!(
+            new_default().encode_preserving("Größe"),
+            new_default().encode_preserving("Grosse")
+        );
+        assert_eq!(
+            new_default().encode_preserving("Cafe\u{301}!"),
+            new_default().encode_preserving("Café!")
+        );
+    }
+
     #[test]
     fn test_sanity_check() {
         enigma::sanity_check('A');
@@ -0,0 +1,210 @@
+use crate::alphabet::{
+    get_position_in_alphabet, letters_from_positions, NUMBER_LETTERS_IN_ALPHABET,
+};
+use crate::message::Message;
+use crate::rotorassembly::all_positions;
+use std::fmt::{Display, Formatter};
+
+/// Number of ring settings a cluster centre is chosen from, used to correct the confidence for
+/// trying all of them.
+const NUMBER_OF_CENTRES: f64 =
+    (NUMBER_LETTERS_IN_ALPHABET * NUMBER_LETTERS_IN_ALPHABET * NUMBER_LETTERS_IN_ALPHABET) as f64;
+
+/// Indicator settings that lie close together, presumably around the ring settings of the day.
+pub struct HerivelCluster {
+    pub ring_settings: [usize; 3],
+    /// First and last letter of the members for each rotor, fast rotor first.
+    pub ranges: [(usize, usize); 3],
+    pub members: usize,
+    pub total: usize,
+    /// One minus the probability that random settings form a cluster this large anywhere.
+    pub confidence: f64,
+}
+
+impl Display for HerivelCluster {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ranges: Vec<String> = self
+            .ranges
+            .iter()
+            .map(|(first, last)| letters_from_positions(&[*first, *last]))
+            .map(|range| format!("{}-{}", &range[0..1], &range[1..2]))
+            .collect();
+        f.write_fmt(format_args!(
+            "ring {}  ranges {}  {}/{} indicators  confidence {:.3}",
+            letters_from_positions(&self.ring_settings),
+            ranges.join(" "),
+            self.members,
+            self.total,
+            self.confidence
+        ))
+    }
+}
+
+/// The Herivel tip: operators set the rings, inserted the rotors and often sent their first
+/// message of the day with an indicator setting only a few letters off the ring settings. The
+/// first triplets of the indicators of these messages are clustered, each member lying at most
+/// `radius` letters away from the centre for every rotor. Returns up to `count` clusters,
+/// largest first.
+pub fn herivel_tip(messages: &[Message], radius: usize, count: usize) -> Vec<HerivelCluster> {
+    let mut remaining: Vec<[usize; 3]> = messages
+        .iter()
+        .map(|message| {
+            let letters: Vec<usize> = message
+                .indicator
+                .get_first_triplet()
+                .chars()
+                .map(get_position_in_alphabet)
+                .collect();
+            letters.try_into().unwrap()
+        })
+        .collect();
+    let total = remaining.len();
+    let mut result = Vec::new();
+    while result.len() < count {
+        let Some(centre) = best_centre(&remaining, radius) else {
+            break;
+        };
+        let (members, rest): (Vec<[usize; 3]>, Vec<[usize; 3]>) = remaining
+            .iter()
+            .partition(|setting| within(centre, **setting, radius));
+        if members.len() < 2 {
+            break;
+        }
+        result.push(HerivelCluster {
+            ring_settings: centre,
+            ranges: std::array::from_fn(|rotor| range(centre[rotor], &members, rotor)),
+            members: members.len(),
+            total,
+            confidence: confidence(total, members.len(), radius),
+        });
+        remaining = rest;
+    }
+    result
+}
+
+/// The centre with the most settings within `radius`, ties broken by the smallest total
+/// distance.
+fn best_centre(settings: &[[usize; 3]], radius: usize) -> Option<[usize; 3]> {
+    all_positions()
+        .map(|centre| {
+            let (members, distance) = settings
+                .iter()
+                .filter(|setting| within(centre, **setting, radius))
+                .fold((0, 0), |(members, distance), setting| {
+                    let sum: usize = (0..3)
+                        .map(|i| distance_on_ring(centre[i], setting[i]))
+                        .sum();
+                    (members + 1, distance + sum)
+                });
+            (centre, members, distance)
+        })
+        .filter(|(_, members, _)| *members > 0)
+        .max_by(|(_, a, x), (_, b, y)| a.cmp(b).then(y.cmp(x)))
+        .map(|(centre, _, _)| centre)
+}
+
+fn within(centre: [usize; 3], setting: [usize; 3], radius: usize) -> bool {
+    (0..3).all(|i| distance_on_ring(centre[i], setting[i]) <= radius)
+}
+
+fn distance_on_ring(a: usize, b: usize) -> usize {
+    let n = NUMBER_LETTERS_IN_ALPHABET;
+    let difference = (a + n - b) % n;
+    difference.min(n - difference)
+}
+
+fn range(centre: usize, members: &[[usize; 3]], rotor: usize) -> (usize, usize) {
+    let n = NUMBER_LETTERS_IN_ALPHABET;
+    // signed offsets from the centre, so that ranges across Z-A are ordered correctly
+    let offsets = members.iter().map(|setting| {
+        let difference = (setting[rotor] + n - centre) % n;
+        if difference > n / 2 {
+            difference as isize - n as isize
+        } else {
+            difference as isize
+        }
+    });
+    let first = offsets.clone().min().unwrap();
+    let last = offsets.max().unwrap();
+    let to_letter = |offset: isize| (centre as isize + offset).rem_euclid(n as isize) as usize;
+    (to_letter(first), to_letter(last))
+}
+
+/// Probability that at least `members` of `total` random settings fall into a given cluster,
+/// times the number of possible centres, subtracted from one.
+fn confidence(total: usize, members: usize, radius: usize) -> f64 {
+    let width = (2 * radius + 1).min(NUMBER_LETTERS_IN_ALPHABET) as f64;
+    let p = (width / NUMBER_LETTERS_IN_ALPHABET as f64).powi(3);
+    let tail: f64 = (members..=total).map(|k| binomial(total, k, p)).sum();
+    1.0 - (tail * NUMBER_OF_CENTRES).min(1.0)
+}
+
+fn binomial(n: usize, k: usize, p: f64) -> f64 {
+    let log_choose: f64 = (0..k)
+        .map(|i| ((n - i) as f64).ln() - ((i + 1) as f64).ln())
+        .sum();
+    (log_choose + k as f64 * p.ln() + (n - k) as f64 * (1.0 - p).ln()).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::herivel::*;
+    use crate::message::Indicator;
+    use crate::random::Random;
+
+    const RING_SETTINGS: [usize; 3] = [4, 19, 25];
+
+    fn message(setting: [usize; 3]) -> Message {
+        let triplet = letters_from_positions(&setting);
+        Message::new(Indicator::new(triplet.repeat(2)), String::new())
+    }
+
+    fn random_messages(random: &mut Random, count: usize) -> Vec<Message> {
+        (0..count)
+            .map(|_| {
+                message(std::array::from_fn(|_| {
+                    random.below(NUMBER_LETTERS_IN_ALPHABET)
+                }))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn finds_ring_settings() {
+        let mut random = Random::new(5);
+        let mut messages = random_messages(&mut random, 20);
+        for _ in 0..10 {
+            let setting = std::array::from_fn(|i| {
+                (RING_SETTINGS[i] + random.below(5) + NUMBER_LETTERS_IN_ALPHABET - 2)
+                    % NUMBER_LETTERS_IN_ALPHABET
+            });
+            messages.push(message(setting));
+        }
+        let clusters = herivel_tip(&messages, 2, 3);
+        let best = &clusters[0];
+        assert!(within(best.ring_settings, RING_SETTINGS, 1));
+        assert!(best.members >= 10);
+        assert!(best.confidence > 0.99);
+        assert_eq!(best.total, 30);
+        // the range of the slow rotor wraps around from X to B
+        assert!(within_letter(best.ranges[2].0, 25, 2) && within_letter(best.ranges[2].1, 25, 2));
+    }
+
+    fn within_letter(a: usize, b: usize, radius: usize) -> bool {
+        distance_on_ring(a, b) <= radius
+    }
+
+    #[test]
+    fn random_settings_give_no_confidence() {
+        let mut random = Random::new(6);
+        let clusters = herivel_tip(&random_messages(&mut random, 30), 2, 1);
+        assert!(clusters[0].confidence < 0.5);
+    }
+
+    #[test]
+    fn test_distance_on_ring() {
+        assert_eq!(distance_on_ring(0, 25), 1);
+        assert_eq!(distance_on_ring(3, 7), 4);
+        assert_eq!(distance_on_ring(0, 13), 13);
+    }
+}
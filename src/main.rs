@@ -4,25 +4,180 @@
 #![warn(clippy::match_bool)]
 
 mod alphabet;
+mod banburismus;
+mod benchmark;
+mod binary;
+mod catalog;
+mod checkpoint;
+mod cillies;
+mod crib;
 mod cryptoattack;
 mod enigma;
+mod herivel;
 mod message;
 mod mode;
+mod naval;
+mod parallel;
+mod parts;
 mod plugboard;
+mod postprocess;
+mod preamble;
+mod profile;
+mod random;
+mod rejewski;
+mod report;
 mod rotor;
 mod rotorassembly;
+mod score;
+mod zygalski;
 
-use crate::cryptoattack::known_plaintext_attack;
+use crate::alphabet::{letters_from_positions, normalise_text};
+use crate::banburismus::{best_alignments, key_distances, longest_chain, right_rotor_candidates};
+use crate::benchmark::{run_benchmarks, Harness};
+use crate::binary::{decrypt_bytes, encrypt_bytes};
+use crate::catalog::{Catalog, DiskCatalog, Setting, SettingLookup};
+use crate::checkpoint::Checkpoint;
+use crate::cillies::{cillie_attack, Dictionary};
+use crate::crib::{drag_cribs, Crib};
+use crate::cryptoattack::{
+    known_plaintext_attack, recover_ring_settings, resume_key_search, Key, KeySpace, SearchEvent,
+};
 use crate::enigma::Enigma;
-use crate::message::{preprocess_for_enigma, Indicator, TEXT};
+use crate::herivel::herivel_tip;
+use crate::message::{
+    preprocess_for_enigma, read_indicators, read_messages, Grouping, Indicator, GROUPS, TEXT,
+};
+use crate::mode::{Characters, Mode, Procedure};
+use crate::naval::{
+    decrypt_naval, encrypt_naval, random_fillers, BigramTable, Kenngruppenbuch, NavalMessage,
+};
+use crate::parts::{decrypt_parts, encrypt_parts, MessagePart, MAX_PART_LETTERS};
 use crate::plugboard::Plugboard;
+use crate::postprocess::{restore, WordList};
+use crate::preamble::{Part, Transmission};
+use crate::profile::Profile;
+use crate::random::Random;
+use crate::rejewski::{characteristic_attack, Characteristic};
+use crate::rotorassembly::{
+    parse_ring_settings, parse_wheel_order, wheel_orders, MachineModel, CYPHER_ROTOR_IDS,
+    REFLECTOR_IDS,
+};
+use crate::score::CribScorer;
+use crate::zygalski::{count_females, read_intercepts, zygalski_attack};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
 use std::iter::zip;
-use std::path::Path;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 const INITIALIZATION: &str = "QRS";
 const ROTOR_SETTINGS: [usize; 3] = [7, 8, 21];
+const RING_SETTINGS: [usize; 3] = [1, 12, 5];
+
+const MINIMUM_OVERLAP: usize = 50;
+const MINIMUM_CILLIES: usize = 2;
+const HERIVEL_RADIUS: usize = 3;
+const HERIVEL_CLUSTERS: usize = 3;
+const DEPTH_THRESHOLD: f64 = 10.0;
+const SEARCH_THRESHOLD: f64 = 0.75;
+const CHECKPOINT_OPTION: &str = "checkpoint=";
+const PLUGBOARD_OPTION: &str = "plugboard=";
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+const BENCHMARK_SAMPLE_TIME: Duration = Duration::from_millis(100);
+
+const USAGE: &str = "usage:
+    enigma                                  encrypt, attack and decrypt a demo message
+    enigma encrypt <indicator> <text> [doubled|single] [groups|naval-groups] [plain|army|spelled]
+                   [drop|preserve|strict]
+    enigma decrypt <indicator> <text> [doubled|single] [groups|naval-groups] [plain|army|spelled]
+                   [drop|preserve|strict]
+                                            encipher with the settings of the demo, the indicator
+                                            is the message key, or the start position followed
+                                            by the message key for the single key procedure,
+                                            printing the text in five or four-letter groups;
+                                            army writes umlauts as AE, full stops as X, numbers
+                                            with the top row between Y, *names* with doubled
+                                            letters and CH as Q, spelled spells out digits;
+                                            decrypt also prints the text with these conventions
+                                            reversed and split into words; accented letters are
+                                            written as their base letters, other characters are
+                                            dropped, kept in place or reported, the conventions
+                                            only go with dropping them
+    enigma encrypt-file <input> <output>    encipher any file to letters in five-letter groups,
+                                            two letters per byte
+    enigma decrypt-file <input> <output>    decipher such letters back to the original bytes
+    enigma split <text> [plain|army|spelled]
+                                            encipher a long text in parts of at most 250 letters,
+                                            each with a random message key
+    enigma join <messages> [plain|army|spelled]
+                                            decipher the parts of a message, in the order of the
+                                            file, and join the texts into readable words
+    enigma intercept <file>                 decipher a transmission written down with its
+                                            preamble, e.g. U6Z DE C 1510 = 49 = EHZ TBS = ...
+    enigma naval encrypt <text>             encipher a naval message with an indicator from the
+                                            Kenngruppenbuch and the bigram table
+    enigma naval decrypt <group> <group> <text>
+                                            decipher a naval message from its indicator groups
+    enigma attack <cyphertext> <crib> [model] [json]
+                                            known plaintext attack on all wheel orders and
+                                            reflectors of enigma-i or m3, as a table or as JSON
+    enigma drag <cyphertext> <crib>...      list the placements of cribs, e.g. ^ANX, WETTER0600$
+    enigma rejewski <indicators> [catalog]  recover the Grundstellung from doubled indicators
+    enigma cillies <indicators> <wheel-order> <rings> [reflector] [dictionary] [plugboard=<file>]
+                                            find the Grundstellung from lazy message keys, keys
+                                            with plugged letters are only found with the
+                                            plugboard of the day
+    enigma catalog <directory> [reflector]  write the characteristic catalog of all wheel orders
+    enigma lookup <catalog> <signature>     list the settings with a characteristic
+    enigma zygalski <intercepts> [reflector]  find ring settings from females of a day
+    enigma herivel <messages> [radius]      find ring settings from first indicators of the day
+    enigma banburismus <messages>           find depths and right hand rotor candidates
+    enigma search <cyphertext> <crib> <offset> [model] [all-rings] [checkpoint=<file>]
+                                            search the message key on all cores, saving
+                                            progress to and resuming from the checkpoint
+    enigma rings <cyphertext> <crib> <offset> <wheel-order> <positions> [reflector]
+                                            recover the ring settings of a found key
+    enigma bench [filter]                   measure throughput, use a release build";
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        None => demo(),
+        Some("encrypt") => encode(&args[1..], Mode::Encrypt),
+        Some("decrypt") => encode(&args[1..], Mode::Decrypt),
+        Some("encrypt-file") => encode_file(&args[1..], Mode::Encrypt),
+        Some("decrypt-file") => encode_file(&args[1..], Mode::Decrypt),
+        Some("split") => split(&args[1..]),
+        Some("join") => join(&args[1..]),
+        Some("intercept") => intercept(&args[1..]),
+        Some("naval") => naval(&args[1..]),
+        Some("attack") => attack(&args[1..]),
+        Some("drag") => drag(&args[1..]),
+        Some("rejewski") => rejewski(&args[1..]),
+        Some("cillies") => cillies(&args[1..]),
+        Some("catalog") => catalog(&args[1..]),
+        Some("lookup") => lookup(&args[1..]),
+        Some("zygalski") => zygalski(&args[1..]),
+        Some("herivel") => herivel(&args[1..]),
+        Some("banburismus") => banburismus(&args[1..]),
+        Some("search") => search(&args[1..]),
+        Some("rings") => rings(&args[1..]),
+        Some("bench") => bench(&args[1..]),
+        Some(_) => exit_with_usage(),
+    }
+}
+
+fn exit_with_usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+}
+
+fn demo() {
     let mut enigma = build_enigma();
 
     let indicator = Indicator::new(INITIALIZATION.to_string());
@@ -34,12 +189,560 @@ fn main() {
     println!("ENCRYPTED MESSAGE:\n{}", encrypted_message);
     println!();
 
-    known_plaintext_attack(&encrypted_message, "WETTERBERICHT".to_string());
+    // the demo knows the wheel order, `enigma attack` searches all of them
+    let key_space = KeySpace {
+        wheel_orders: vec![["I", "II", "III"]],
+        reflector_ids: vec!["B"],
+        ring_settings: vec![[0, 0, 0]],
+    };
+    let report =
+        known_plaintext_attack(&encrypted_message, "WETTERBERICHT".to_string(), &key_space);
+    println!("{}", report);
     println!();
-    known_plaintext_attack(
+    let report = known_plaintext_attack(
         &encrypted_message,
         "WETTERBERICHTNULLSECHSNULLNULL".to_string(),
+        &key_space,
+    );
+    println!("{}", report);
+    println!();
+
+    let decrypted_message = build_enigma().decrypt(encrypted_message);
+    println!("DECRYPTED MESSAGE:\n{}", decrypted_message);
+}
+
+fn encode(args: &[String], mode: Mode) {
+    let [indicator, text, options @ ..] = args else {
+        exit_with_usage()
+    };
+    let mut procedure = Procedure::DoubledKey;
+    let mut grouping = None;
+    let mut profile = None;
+    let mut characters = Characters::Drop;
+    for option in options {
+        if let Some(parsed) = Procedure::parse(option) {
+            procedure = parsed;
+        } else if let Some(parsed) = Characters::parse(option) {
+            characters = parsed;
+        } else if let Some(parsed) = Grouping::parse(option) {
+            grouping = Some(parsed);
+        } else if let Some(parsed) = Profile::parse(option) {
+            profile = Some(parsed);
+        } else {
+            exit_with_usage();
+        }
+    }
+    // a profile writes everything it has no rule for as letters or drops it
+    if profile.is_some() && characters != Characters::Drop {
+        exit_with_usage();
+    }
+    let text = match (&mode, characters, profile) {
+        (_, Characters::Preserve, _) => text.clone(),
+        (Mode::Encrypt, Characters::Drop, Some(profile)) => profile.apply(text),
+        (_, Characters::Drop, _) => preprocess_for_enigma(text),
+        (_, Characters::Strict, _) => normalise_strictly(text, &mode),
+    };
+    let message = message::Message::new(Indicator::new(preprocess_for_enigma(indicator)), text);
+    let mut enigma = build_enigma();
+    let result = match (&mode, characters) {
+        (Mode::Encrypt, Characters::Preserve) => enigma.encrypt_preserving(message, procedure),
+        (Mode::Decrypt, Characters::Preserve) => enigma.decrypt_preserving(message, procedure),
+        (Mode::Encrypt, _) => enigma.encrypt_with(message, procedure),
+        (Mode::Decrypt, _) => enigma.decrypt_with(message, procedure),
+    };
+    if let (Mode::Decrypt, Some(profile)) = (&mode, profile) {
+        println!("{}", result);
+        let words = WordList::from_file(Path::new(postprocess::PATH));
+        println!("{}", restore(&result.text, &profile, &words));
+        return;
+    }
+    match grouping {
+        Some(grouping) => println!("{}", result.format_with(grouping)),
+        None => println!("{}", result),
+    }
+}
+
+fn encode_file(args: &[String], mode: Mode) {
+    let [input, output] = args else {
+        exit_with_usage()
+    };
+    let mut enigma = build_enigma();
+    match mode {
+        Mode::Encrypt => {
+            let cyphertext = encrypt_bytes(&mut enigma, &fs::read(input).unwrap());
+            fs::write(output, GROUPS.format(&cyphertext) + "\n").unwrap();
+        }
+        Mode::Decrypt => {
+            let cyphertext = fs::read_to_string(input).unwrap();
+            fs::write(output, decrypt_bytes(&mut enigma, &cyphertext)).unwrap();
+        }
+    }
+}
+
+/// Cyphertext may be written in groups, so decrypting also allows whitespace. Positions are
+/// reported in the text as typed.
+fn normalise_strictly(text: &str, mode: &Mode) -> String {
+    let mut error = match normalise_text(text) {
+        Ok(letters) => return letters,
+        Err(error) => error,
+    };
+    if matches!(mode, Mode::Decrypt) {
+        error.characters.retain(|(_, c)| !c.is_whitespace());
+        if error.characters.is_empty() {
+            return preprocess_for_enigma(text);
+        }
+    }
+    eprintln!("{}", error);
+    process::exit(1);
+}
+
+fn split(args: &[String]) {
+    let (text, profile) = match args {
+        [text] => (text, profile::PLAIN),
+        [text, profile] => (
+            text,
+            Profile::parse(profile).unwrap_or_else(|| exit_with_usage()),
+        ),
+        _ => exit_with_usage(),
+    };
+    let parts = encrypt_parts(
+        &build_enigma(),
+        &profile.apply(text),
+        MAX_PART_LETTERS,
+        &mut Random::new(clock_seed()),
+    );
+    for part in parts {
+        println!("{}", part.part);
+        println!("{}", part.message.format_with(GROUPS));
+    }
+}
+
+fn join(args: &[String]) {
+    let (path, profile) = match args {
+        [path] => (path, profile::PLAIN),
+        [path, profile] => (
+            path,
+            Profile::parse(profile).unwrap_or_else(|| exit_with_usage()),
+        ),
+        _ => exit_with_usage(),
+    };
+    let messages = read_messages(Path::new(path));
+    let total = messages.len();
+    let parts = messages
+        .into_iter()
+        .enumerate()
+        .map(|(i, message)| MessagePart {
+            part: Part {
+                number: i + 1,
+                total,
+            },
+            message,
+        })
+        .collect();
+    let text = decrypt_parts(&build_enigma(), parts);
+    let words = WordList::from_file(Path::new(postprocess::PATH));
+    println!("{}", text);
+    println!("{}", restore(&text, &profile, &words));
+}
+
+fn intercept(args: &[String]) {
+    let [path] = args else { exit_with_usage() };
+    let transmission = Transmission::parse(&fs::read_to_string(path).unwrap());
+    let decrypted = build_enigma().decrypt_with(transmission.message(), Procedure::SingleKey);
+    println!("{}", transmission.preamble);
+    println!("Kenngruppe: {}", transmission.kenngruppe());
+    println!("{}", decrypted);
+}
+
+fn naval(args: &[String]) {
+    let table = BigramTable::from_file(Path::new(naval::BIGRAM_TABLE_PATH));
+    let mut enigma = build_enigma();
+    match args {
+        [mode, text] if mode == "encrypt" => {
+            let mut random = Random::new(clock_seed());
+            let book = Kenngruppenbuch::from_file(Path::new(naval::KENNGRUPPENBUCH_PATH));
+            let indicator = book.choose(&mut random);
+            let fillers = random_fillers(&mut random);
+            let text = preprocess_for_enigma(text);
+            println!(
+                "{}",
+                encrypt_naval(&mut enigma, &indicator, fillers, &table, &text)
+            );
+        }
+        [mode, first, second, text] if mode == "decrypt" => {
+            let message = NavalMessage {
+                groups: [preprocess_for_enigma(first), preprocess_for_enigma(second)],
+                text: preprocess_for_enigma(text),
+            };
+            let (indicator, text) = decrypt_naval(&mut enigma, &message, &table);
+            println!(
+                "Kenngruppe: {}, Spruchschluessel: {}\ntext: {}",
+                indicator.kenngruppe, indicator.spruchschluessel, text
+            );
+        }
+        _ => exit_with_usage(),
+    }
+}
+
+fn attack(args: &[String]) {
+    let [cyphertext, crib, options @ ..] = args else {
+        exit_with_usage()
+    };
+    let (model, json) = parse_options(options, "json");
+    let message = message::Message::new(
+        Indicator::new(INITIALIZATION.to_string()),
+        preprocess_for_enigma(cyphertext),
+    );
+    let key_space = KeySpace::for_model(model, vec![[0, 0, 0]]);
+    let report = known_plaintext_attack(&message, normalise_crib(crib), &key_space);
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        println!("{}", report);
+    }
+}
+
+/// See [`Crib::normalise_spec`], a crib without letters is a usage error.
+fn normalise_crib(crib: &str) -> String {
+    let spec = Crib::normalise_spec(crib);
+    if spec
+        .trim_start_matches('^')
+        .trim_end_matches('$')
+        .is_empty()
+    {
+        exit_with_usage();
+    }
+    spec
+}
+
+fn drag(args: &[String]) {
+    let [cyphertext, cribs @ ..] = args else {
+        exit_with_usage()
+    };
+    if cribs.is_empty() {
+        exit_with_usage();
+    }
+    let cribs: Vec<Crib> = cribs
+        .iter()
+        .map(|crib| Crib::parse(&normalise_crib(crib)))
+        .collect();
+    for result in drag_cribs(&preprocess_for_enigma(cyphertext), &cribs) {
+        println!("{}: {} placements", result.crib, result.placements.len());
+        for placement in result.placements {
+            println!("{}", placement);
+        }
+    }
+}
+
+fn rejewski(args: &[String]) {
+    let (indicator_path, catalog_directory) = match args {
+        [path] => (path, None),
+        [path, directory] => (path, Some(Path::new(directory))),
+        _ => exit_with_usage(),
+    };
+    let indicators = read_indicators(Path::new(indicator_path));
+    let result = match catalog_directory {
+        Some(directory) => characteristic_attack(&indicators, &DiskCatalog::open(directory)),
+        None => characteristic_attack(&indicators, &generate_catalog("B")),
+    };
+    match result {
+        None => println!("indicators do not determine the characteristic, collect more"),
+        Some((characteristic, settings)) => {
+            println!("characteristic: {}", characteristic);
+            print_settings(&settings);
+        }
+    }
+}
+
+fn cillies(args: &[String]) {
+    let [indicator_path, wheel_order, ring_settings, options @ ..] = args else {
+        exit_with_usage()
+    };
+    let mut reflector = "B";
+    let mut dictionary_path = cillies::PATH;
+    let mut plugboard = Plugboard::new(vec![]);
+    for option in options {
+        if let Some(path) = option.strip_prefix(PLUGBOARD_OPTION) {
+            plugboard = Plugboard::from_file(Path::new(path));
+        } else if let Some(id) = REFLECTOR_IDS.iter().find(|id| *id == option) {
+            reflector = id;
+        } else {
+            dictionary_path = option;
+        }
+    }
+    let mut enigma = Enigma::new(
+        rotorassembly::RotorAssembly::from_file(
+            Path::new(rotor::PATH),
+            parse_wheel_order(wheel_order),
+            reflector,
+        ),
+        plugboard,
+    );
+    enigma.set_ring_settings(parse_ring_settings(ring_settings));
+    let candidates = cillie_attack(
+        &enigma,
+        &read_indicators(Path::new(indicator_path)),
+        &Dictionary::from_file(Path::new(dictionary_path)),
+        MINIMUM_CILLIES,
+    );
+    println!("{} candidates:", candidates.len());
+    for candidate in candidates {
+        println!("{}", candidate);
+    }
+}
+
+fn catalog(args: &[String]) {
+    let (directory, reflector_id) = match args {
+        [directory] => (directory, "B"),
+        [directory, reflector] => (directory, reflector.as_str()),
+        _ => exit_with_usage(),
+    };
+    generate_catalog(reflector_id).write(Path::new(directory));
+}
+
+fn lookup(args: &[String]) {
+    let [directory, signature] = args else {
+        exit_with_usage();
+    };
+    let catalog = DiskCatalog::open(Path::new(directory));
+    println!("reflector: {}", catalog.reflector_id());
+    print_settings(&catalog.lookup(&Characteristic::from_signature(signature)));
+}
+
+fn zygalski(args: &[String]) {
+    let (intercept_path, reflector_id) = match args {
+        [path] => (path, "B"),
+        [path, reflector] => (path, reflector.as_str()),
+        _ => exit_with_usage(),
+    };
+    let intercepts = read_intercepts(Path::new(intercept_path));
+    println!("females: {}", count_females(&intercepts));
+    let wheel_orders = wheel_orders(&CYPHER_ROTOR_IDS);
+    let candidates = zygalski_attack(
+        Path::new(rotor::PATH),
+        &intercepts,
+        &wheel_orders,
+        reflector_id,
+    );
+    println!("{} candidates:", candidates.len());
+    for candidate in candidates {
+        println!("{}", candidate);
+    }
+}
+
+fn herivel(args: &[String]) {
+    let (message_path, radius) = match args {
+        [path] => (path, HERIVEL_RADIUS),
+        [path, radius] => (path, radius.parse().unwrap_or_else(|_| exit_with_usage())),
+        _ => exit_with_usage(),
+    };
+    let messages = read_messages(Path::new(message_path));
+    for cluster in herivel_tip(&messages, radius, HERIVEL_CLUSTERS) {
+        println!("{}", cluster);
+    }
+}
+
+fn banburismus(args: &[String]) {
+    let [message_path] = args else {
+        exit_with_usage();
+    };
+    let messages = read_messages(Path::new(message_path));
+    let alignments = best_alignments(&messages, MINIMUM_OVERLAP);
+    println!("strongest pairings:");
+    for alignment in alignments.iter().take(20) {
+        println!("{}", alignment);
+    }
+
+    let distances = key_distances(&messages, &alignments, DEPTH_THRESHOLD);
+    let chain = longest_chain(&messages, &distances);
+    println!();
+    println!(
+        "longest chain: {} keys, {} conflicts",
+        chain.relative_keys.len(),
+        chain.conflicts
+    );
+    let mut relative_keys: Vec<_> = chain.relative_keys.iter().collect();
+    relative_keys.sort();
+    for (message, key) in relative_keys {
+        println!("message {:>3}  fast rotor +{}", message, key);
+    }
+
+    println!();
+    println!("right hand rotor candidates:");
+    let candidates = right_rotor_candidates(
+        Path::new(rotor::PATH),
+        &chain,
+        &distances,
+        &CYPHER_ROTOR_IDS,
     );
+    for candidate in candidates {
+        println!(
+            "{:<4} first key of chain: {}",
+            candidate.rotor_id,
+            letters_from_positions(&candidate.keys)
+        );
+    }
+}
+
+fn search(args: &[String]) {
+    let [cyphertext, crib, offset, options @ ..] = args else {
+        exit_with_usage()
+    };
+    let (checkpoint_path, options): (Vec<&String>, Vec<&String>) = options
+        .iter()
+        .partition(|option| option.starts_with(CHECKPOINT_OPTION));
+    let checkpoint_path = checkpoint_path
+        .first()
+        .map(|option| PathBuf::from(&option[CHECKPOINT_OPTION.len()..]));
+    let options: Vec<String> = options.into_iter().cloned().collect();
+    let (model, all_rings) = parse_options(&options, "all-rings");
+    let offset = offset.parse().unwrap_or_else(|_| exit_with_usage());
+    let ring_settings = if all_rings {
+        KeySpace::all_ring_settings()
+    } else {
+        vec![[0, 0, 0]]
+    };
+    let key_space = KeySpace::for_model(model, ring_settings);
+    let cyphertext = preprocess_for_enigma(cyphertext);
+    let scorer = Arc::new(CribScorer::new(preprocess_for_enigma(crib), offset));
+    let mut checkpoint = match &checkpoint_path {
+        Some(path) if path.exists() => Checkpoint::load(path),
+        _ => Checkpoint::new(&key_space, &cyphertext, scorer.as_ref(), SEARCH_THRESHOLD),
+    };
+    for candidate in &checkpoint.candidates {
+        println!("{:.2}  {}", candidate.score, candidate.key);
+    }
+    if checkpoint.is_complete() {
+        eprintln!("the checkpoint covers the whole key space");
+        return;
+    }
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let search = resume_key_search(
+        Path::new(rotor::PATH),
+        key_space,
+        &Plugboard::new(vec![]),
+        cyphertext,
+        scorer,
+        SEARCH_THRESHOLD,
+        threads,
+        &checkpoint,
+    );
+    let mut last_save = Instant::now();
+    for event in search.events() {
+        checkpoint.record(&event);
+        match event {
+            SearchEvent::Candidate { candidate, .. } => {
+                eprintln!();
+                println!("{:.2}  {}", candidate.score, candidate.key);
+                if candidate.score == 1.0 {
+                    search.cancel();
+                }
+            }
+            SearchEvent::Progress {
+                searched, total, ..
+            } => {
+                eprint!("\rsearched {}/{} keys", searched, total);
+                io::stderr().flush().unwrap();
+            }
+        }
+        if let Some(path) = &checkpoint_path {
+            if last_save.elapsed() >= CHECKPOINT_INTERVAL {
+                checkpoint.save(path);
+                last_save = Instant::now();
+            }
+        }
+    }
+    eprintln!();
+    search.join();
+    if let Some(path) = &checkpoint_path {
+        checkpoint.save(path);
+    }
+}
+
+fn rings(args: &[String]) {
+    let (cyphertext, crib, offset, wheel_order, positions, reflector) = match args {
+        [cyphertext, crib, offset, wheel_order, positions] => {
+            (cyphertext, crib, offset, wheel_order, positions, "B")
+        }
+        [cyphertext, crib, offset, wheel_order, positions, reflector] => (
+            cyphertext,
+            crib,
+            offset,
+            wheel_order,
+            positions,
+            *REFLECTOR_IDS
+                .iter()
+                .find(|id| *id == reflector)
+                .unwrap_or_else(|| exit_with_usage()),
+        ),
+        _ => exit_with_usage(),
+    };
+    let offset = offset.parse().unwrap_or_else(|_| exit_with_usage());
+    let setting = Setting::parse(&format!("{} {}", wheel_order, positions));
+    let key = Key {
+        wheel_order: setting.wheel_order,
+        reflector,
+        ring_settings: [0, 0, 0],
+        positions: setting.positions,
+    };
+    let best = recover_ring_settings(
+        Path::new(rotor::PATH),
+        key,
+        &Plugboard::new(vec![]),
+        &preprocess_for_enigma(cyphertext),
+        &CribScorer::new(preprocess_for_enigma(crib), offset),
+    );
+    for candidate in best {
+        println!("{:.2}  {}", candidate.score, candidate.key);
+    }
+}
+
+/// Parses a machine model and a flag, both optional and in any order.
+fn parse_options(options: &[String], flag: &str) -> (MachineModel, bool) {
+    let mut model = MachineModel::EnigmaI;
+    let mut flag_set = false;
+    for option in options {
+        if option == flag {
+            flag_set = true;
+        } else {
+            model = MachineModel::parse(option).unwrap_or_else(|| exit_with_usage());
+        }
+    }
+    (model, flag_set)
+}
+
+fn bench(args: &[String]) {
+    let filter = match args {
+        [] => None,
+        [filter] => Some(filter.clone()),
+        _ => exit_with_usage(),
+    };
+    if cfg!(debug_assertions) {
+        eprintln!("warning: benchmarking a debug build");
+    }
+    let harness = Harness::new(BENCHMARK_SAMPLE_TIME, filter);
+    for measurement in run_benchmarks(&harness) {
+        println!("{}", measurement);
+    }
+}
+
+fn generate_catalog(reflector_id: &str) -> Catalog {
+    let wheel_orders = wheel_orders(&CYPHER_ROTOR_IDS);
+    Catalog::generate(Path::new(rotor::PATH), &wheel_orders, reflector_id)
+}
+
+fn print_settings(settings: &[Setting]) {
+    println!("{} candidate settings:", settings.len());
+    for setting in settings {
+        println!("{}", setting);
+    }
+}
+
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64
 }
 
 fn build_enigma() -> Enigma {
@@ -58,5 +761,7 @@ fn build_enigma() -> Enigma {
     }
 
     let assembly = rotorassembly::RotorAssembly::new(rotors, reflector);
-    Enigma::new(assembly, plugboard)
+    let mut enigma = Enigma::new(assembly, plugboard);
+    enigma.set_ring_settings(RING_SETTINGS);
+    enigma
 }
@@ -1,11 +1,33 @@
-use crate::alphabet::{is_capital_letter, is_small_letter};
-use crate::mode::Mode;
+use crate::alphabet::normalise;
+use crate::mode::{Mode, Procedure};
 use std::fmt::{Display, Formatter};
+use std::fs;
+use std::iter::zip;
+use std::path::Path;
 
 pub const TEXT: &str =
     "Wetterbericht null sechs null null. Wind null drei null, Staerke vier. Leichter Regen. \
     Bedeckt. Gelegentlicher Nebel. Drei Liter.";
 
+/// How cyphertext is written down, in groups of letters with a number of groups per line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grouping {
+    pub group_length: usize,
+    pub groups_per_line: usize,
+}
+
+/// Five-letter groups of army and air force traffic.
+pub const GROUPS: Grouping = Grouping {
+    group_length: 5,
+    groups_per_line: 10,
+};
+
+/// Four-letter groups of naval traffic.
+pub const NAVAL_GROUPS: Grouping = Grouping {
+    group_length: 4,
+    groups_per_line: 12,
+};
+
 pub struct Message {
     pub indicator: Indicator,
     pub text: String,
@@ -17,10 +39,15 @@ pub struct Indicator {
 
 impl Indicator {
     pub fn sanity_check(&self, mode: &Mode) {
+        self.sanity_check_for(mode, Procedure::DoubledKey);
+    }
+
+    pub fn sanity_check_for(&self, mode: &Mode, procedure: Procedure) {
         let length = self.value.len();
-        match mode {
-            Mode::Decrypt => Self::check_length(length, 6),
-            Mode::Encrypt => Self::check_length(length, 3),
+        match (procedure, mode) {
+            (Procedure::DoubledKey, Mode::Decrypt) => Self::check_length(length, 6),
+            (Procedure::DoubledKey, Mode::Encrypt) => Self::check_length(length, 3),
+            (Procedure::SingleKey, _) => Self::check_length(length, 6),
         }
     }
 
@@ -45,12 +72,102 @@ impl Indicator {
         );
         &self.value[3..6]
     }
+
+    /// Positions within the triplets at which a doubled indicator repeats a letter.
+    pub fn females(&self) -> Vec<usize> {
+        zip(
+            self.get_first_triplet().chars(),
+            self.get_second_triplet().chars(),
+        )
+        .enumerate()
+        .filter(|(_, (first, second))| first == second)
+        .map(|(i, _)| i)
+        .collect()
+    }
+}
+
+/// The trimmed lines of a data file. Empty lines and lines starting with `#` are skipped.
+pub fn data_lines(path: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Reads one enciphered six-letter indicator per line, see [`data_lines`].
+pub fn read_indicators(path: &Path) -> Vec<Indicator> {
+    data_lines(path)
+        .into_iter()
+        .map(|line| {
+            let indicator = Indicator::new(line);
+            indicator.sanity_check(&Mode::Decrypt);
+            indicator
+        })
+        .collect()
+}
+
+/// Reads one message per line, see [`data_lines`], written as the enciphered indicator followed
+/// by the cyphertext, which may be split into groups.
+pub fn read_messages(path: &Path) -> Vec<Message> {
+    data_lines(path)
+        .iter()
+        .map(|line| {
+            let (indicator, text) = line
+                .split_once(' ')
+                .expect("message must consist of indicator and text");
+            let indicator = Indicator::new(indicator.to_string());
+            indicator.sanity_check(&Mode::Decrypt);
+            Message::new(indicator, ungroup(text))
+        })
+        .collect()
 }
 
 impl Message {
     pub fn new(indicator: Indicator, text: String) -> Self {
         Message { indicator, text }
     }
+
+    /// Like [`Display`], but with the text written in groups on lines of their own.
+    pub fn format_with(&self, grouping: Grouping) -> String {
+        format!(
+            "indicator: {}  \ntext:\n{}",
+            self.indicator.value,
+            grouping.format(&self.text)
+        )
+    }
+}
+
+impl Grouping {
+    pub fn parse(name: &str) -> Option<Grouping> {
+        match name {
+            "groups" => Some(GROUPS),
+            "naval-groups" => Some(NAVAL_GROUPS),
+            _ => None,
+        }
+    }
+
+    /// Splits `text` into groups separated by spaces and wraps the lines. The last group may be
+    /// shorter.
+    pub fn format(self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let groups: Vec<String> = chars
+            .chunks(self.group_length)
+            .map(|group| group.iter().collect())
+            .collect();
+        groups
+            .chunks(self.groups_per_line)
+            .map(|line| line.join(" "))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Removes the spaces and line breaks of grouped text.
+pub fn ungroup(text: &str) -> String {
+    text.split_whitespace().collect()
 }
 
 impl Display for Message {
@@ -62,29 +179,54 @@ impl Display for Message {
     }
 }
 
+/// Keeps the letters, see [`normalise`], and drops everything else.
 pub fn preprocess_for_enigma(message: &str) -> String {
-    message
-        .chars()
-        .filter_map(preprocess_char)
-        .collect::<String>()
-}
-
-fn preprocess_char(input: char) -> Option<char> {
-    if is_small_letter(input) {
-        return Some(input.to_uppercase().next().unwrap());
-    } else if is_capital_letter(input) {
-        return Some(input);
-    }
-    None
+    message.chars().filter_map(normalise).collect::<String>()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::message::preprocess_for_enigma;
+    use crate::message::*;
+    use crate::naval::BIGRAM_TABLE_PATH;
+
+    #[test]
+    fn data_lines_skip_comments() {
+        let lines = data_lines(Path::new(BIGRAM_TABLE_PATH));
+        assert_eq!(lines.len(), 26 * 26 / 2);
+        assert_eq!(lines[0], "IB SA");
+    }
 
     #[test]
     fn test_preprocess_for_enigma() {
         assert_eq!(preprocess_for_enigma("aBc D*\nyz"), "ABCDYZ");
+        assert_eq!(preprocess_for_enigma("Café Straße"), "CAFESTRASSE");
         assert_eq!(preprocess_for_enigma(""), "");
     }
+
+    #[test]
+    fn format_groups() {
+        let grouping = Grouping {
+            group_length: 5,
+            groups_per_line: 2,
+        };
+        assert_eq!(
+            grouping.format("ABCDEFGHIJKLMNOPQRSTUVW"),
+            "ABCDE FGHIJ\nKLMNO PQRST\nUVW"
+        );
+        assert_eq!(NAVAL_GROUPS.format("ABCDEFGHIJ"), "ABCD EFGH IJ");
+        assert_eq!(GROUPS.format(""), "");
+    }
+
+    #[test]
+    fn ungroup_text() {
+        let text = "ABCDEFGHIJKLMNOPQRSTUVW";
+        assert_eq!(ungroup(&GROUPS.format(text)), text);
+        assert_eq!(ungroup(" ABCD EFGH\r\n  IJ\t"), "ABCDEFGHIJ");
+    }
+
+    #[test]
+    fn females() {
+        assert_eq!(Indicator::new("ABCDEF".to_string()).females(), vec![]);
+        assert_eq!(Indicator::new("ABCAEC".to_string()).females(), vec![0, 2]);
+    }
 }
@@ -2,3 +2,48 @@ pub enum Mode {
     Encrypt,
     Decrypt,
 }
+
+/// How the message key is passed on in the indicator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Procedure {
+    /// Until May 1940: the message key is enciphered twice at the Grundstellung of the day. The
+    /// plain indicator is the message key, the enciphered one has six letters.
+    DoubledKey,
+    /// Heer and Luftwaffe from May 1940: the operator picks a start position, sends it in clear
+    /// and enciphers the message key once at it. Both the plain and the enciphered indicator
+    /// consist of the start position followed by the message key.
+    SingleKey,
+}
+
+impl Procedure {
+    pub fn parse(name: &str) -> Option<Procedure> {
+        match name {
+            "doubled" => Some(Procedure::DoubledKey),
+            "single" => Some(Procedure::SingleKey),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to characters of the text that are not capital letters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Characters {
+    /// Letters are capitalised, everything else is removed before enciphering.
+    Drop,
+    /// Letters are enciphered keeping their case, everything else stays where it is and does
+    /// not step the rotors.
+    Preserve,
+    /// Anything but letters is reported as an error.
+    Strict,
+}
+
+impl Characters {
+    pub fn parse(name: &str) -> Option<Characters> {
+        match name {
+            "drop" => Some(Characters::Drop),
+            "preserve" => Some(Characters::Preserve),
+            "strict" => Some(Characters::Strict),
+            _ => None,
+        }
+    }
+}
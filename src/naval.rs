@@ -0,0 +1,283 @@
+use crate::alphabet::{get_position_in_alphabet, is_capital_letter, ALPHABET};
+use crate::enigma::Enigma;
+use crate::message::{data_lines, ungroup, NAVAL_GROUPS};
+use crate::random::Random;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+pub const KENNGRUPPENBUCH_PATH: &str = "src/kenngruppenbuch.txt";
+pub const BIGRAM_TABLE_PATH: &str = "src/bigrams.txt";
+
+/// Trigrams from which the operator picks Kenngruppe and Spruchschlüssel.
+pub struct Kenngruppenbuch {
+    trigrams: Vec<String>,
+}
+
+/// Doppelbuchstabentauschtafel, a reciprocal substitution of all bigrams.
+pub struct BigramTable {
+    swaps: HashMap<[char; 2], [char; 2]>,
+}
+
+/// The plain naval indicator. The Kenngruppe identifies the key net, the Spruchschlüssel
+/// enciphered at the Grundstellung gives the message key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NavalIndicator {
+    pub kenngruppe: String,
+    pub spruchschluessel: String,
+}
+
+/// A naval message as sent, two four-letter indicator groups followed by the cyphertext.
+pub struct NavalMessage {
+    pub groups: [String; 2],
+    pub text: String,
+}
+
+impl Kenngruppenbuch {
+    /// Reads one trigram per line, see [`data_lines`].
+    pub fn from_file(path: &Path) -> Kenngruppenbuch {
+        let trigrams = data_lines(path);
+        for trigram in &trigrams {
+            assert_trigram(trigram);
+        }
+        assert!(trigrams.len() >= 2, "Kenngruppenbuch needs two trigrams");
+        Kenngruppenbuch { trigrams }
+    }
+
+    /// Picks two different trigrams, as the operator did from the book.
+    pub fn choose(&self, random: &mut Random) -> NavalIndicator {
+        let kenngruppe = random.below(self.trigrams.len());
+        let mut spruchschluessel = random.below(self.trigrams.len() - 1);
+        if spruchschluessel >= kenngruppe {
+            spruchschluessel += 1;
+        }
+        NavalIndicator::new(&self.trigrams[kenngruppe], &self.trigrams[spruchschluessel])
+    }
+}
+
+impl BigramTable {
+    /// Reads two bigrams per line, see [`data_lines`], that are swapped for each other. Every
+    /// bigram must appear exactly once.
+    pub fn from_file(path: &Path) -> BigramTable {
+        let mut swaps = HashMap::new();
+        for line in data_lines(path) {
+            let (first, second) = line.split_once(' ').expect("line must contain two bigrams");
+            let (first, second) = (to_bigram(first), to_bigram(second));
+            for (from, to) in [(first, second), (second, first)] {
+                assert!(
+                    swaps.insert(from, to).is_none(),
+                    "bigram {}{} appears twice",
+                    from[0],
+                    from[1]
+                );
+            }
+        }
+        assert_eq!(
+            swaps.len(),
+            ALPHABET.len() * ALPHABET.len(),
+            "table must contain every bigram"
+        );
+        BigramTable { swaps }
+    }
+
+    /// Swapping is its own inverse, so this also reverses the substitution.
+    pub fn swap(&self, bigram: [char; 2]) -> [char; 2] {
+        self.swaps[&bigram]
+    }
+}
+
+impl NavalIndicator {
+    pub fn new(kenngruppe: &str, spruchschluessel: &str) -> NavalIndicator {
+        assert_trigram(kenngruppe);
+        assert_trigram(spruchschluessel);
+        NavalIndicator {
+            kenngruppe: kenngruppe.to_string(),
+            spruchschluessel: spruchschluessel.to_string(),
+        }
+    }
+
+    /// Writes the Kenngruppe behind a filler and the Spruchschlüssel in front of another into a
+    /// grid of two rows, then substitutes the vertical bigrams:
+    ///
+    /// ```text
+    /// f K K K      a a a a
+    /// S S S f  ->  b b b b
+    /// ```
+    pub fn to_groups(&self, fillers: [char; 2], table: &BigramTable) -> [String; 2] {
+        let top: Vec<char> = [fillers[0]]
+            .into_iter()
+            .chain(self.kenngruppe.chars())
+            .collect();
+        let bottom: Vec<char> = self.spruchschluessel.chars().chain([fillers[1]]).collect();
+        let swapped: Vec<[char; 2]> = (0..4)
+            .map(|column| table.swap([top[column], bottom[column]]))
+            .collect();
+        [
+            swapped.iter().map(|bigram| bigram[0]).collect(),
+            swapped.iter().map(|bigram| bigram[1]).collect(),
+        ]
+    }
+
+    /// Reverses [`NavalIndicator::to_groups`].
+    pub fn from_groups(groups: &[String; 2], table: &BigramTable) -> NavalIndicator {
+        let top: Vec<char> = groups[0].chars().collect();
+        let bottom: Vec<char> = groups[1].chars().collect();
+        assert!(
+            top.len() == 4 && bottom.len() == 4,
+            "indicator groups must have four letters"
+        );
+        let columns: Vec<[char; 2]> = (0..4)
+            .map(|column| table.swap([top[column], bottom[column]]))
+            .collect();
+        let kenngruppe: String = columns[1..].iter().map(|bigram| bigram[0]).collect();
+        let spruchschluessel: String = columns[..3].iter().map(|bigram| bigram[1]).collect();
+        NavalIndicator::new(&kenngruppe, &spruchschluessel)
+    }
+}
+
+impl Display for NavalMessage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "indicator: {} {}  \ntext:\n{}",
+            self.groups[0],
+            self.groups[1],
+            NAVAL_GROUPS.format(&self.text)
+        ))
+    }
+}
+
+/// Enciphers the Spruchschlüssel at the current position, the Grundstellung, to get the message
+/// key, and the text at the message key.
+pub fn encrypt_naval(
+    enigma: &mut Enigma,
+    indicator: &NavalIndicator,
+    fillers: [char; 2],
+    table: &BigramTable,
+    text: &str,
+) -> NavalMessage {
+    set_message_key(enigma, indicator);
+    NavalMessage {
+        groups: indicator.to_groups(fillers, table),
+        text: enigma.encode_message(text),
+    }
+}
+
+/// Recovers the indicator from the groups and deciphers the text, which may be written in groups,
+/// with the machine at the Grundstellung.
+pub fn decrypt_naval(
+    enigma: &mut Enigma,
+    message: &NavalMessage,
+    table: &BigramTable,
+) -> (NavalIndicator, String) {
+    let indicator = NavalIndicator::from_groups(&message.groups, table);
+    set_message_key(enigma, &indicator);
+    let text = enigma.encode_message(&ungroup(&message.text));
+    (indicator, text)
+}
+
+/// Fillers of the grid, random letters.
+pub fn random_fillers(random: &mut Random) -> [char; 2] {
+    [
+        ALPHABET[random.below(ALPHABET.len())],
+        ALPHABET[random.below(ALPHABET.len())],
+    ]
+}
+
+fn set_message_key(enigma: &mut Enigma, indicator: &NavalIndicator) {
+    let key = enigma.encode_message(&indicator.spruchschluessel);
+    let positions: Vec<usize> = key.chars().map(get_position_in_alphabet).collect();
+    enigma.set_positions(positions.try_into().unwrap());
+}
+
+fn assert_trigram(value: &str) {
+    assert!(
+        value.len() == 3 && value.chars().all(is_capital_letter),
+        "{} must be three capital letters",
+        value
+    );
+}
+
+fn to_bigram(value: &str) -> [char; 2] {
+    let chars: Vec<char> = value.chars().collect();
+    assert!(
+        chars.len() == 2 && chars.iter().all(|c| is_capital_letter(*c)),
+        "{} must be two capital letters",
+        value
+    );
+    [chars[0], chars[1]]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::naval::*;
+    use crate::rotorassembly::RotorAssembly;
+    use crate::{plugboard, Plugboard};
+
+    const GRUNDSTELLUNG: [usize; 3] = [20, 2, 9];
+
+    fn table() -> BigramTable {
+        BigramTable::from_file(Path::new(BIGRAM_TABLE_PATH))
+    }
+
+    fn enigma() -> Enigma {
+        let assembly =
+            RotorAssembly::from_file(Path::new(crate::rotor::PATH), ["VI", "II", "VIII"], "B");
+        let mut enigma = Enigma::new(assembly, Plugboard::from_file(Path::new(plugboard::PATH)));
+        enigma.set_positions(GRUNDSTELLUNG);
+        enigma
+    }
+
+    #[test]
+    fn bigram_table_is_reciprocal() {
+        let table = table();
+        for a in ALPHABET {
+            for b in ALPHABET {
+                assert_eq!(table.swap(table.swap([a, b])), [a, b]);
+            }
+        }
+    }
+
+    #[test]
+    fn indicator_groups() {
+        let table = table();
+        let indicator = NavalIndicator::new("KJH", "BNM");
+        let groups = indicator.to_groups(['Q', 'W'], &table);
+        assert_eq!(
+            table.swap(['Q', 'B']),
+            [
+                groups[0].as_bytes()[0] as char,
+                groups[1].as_bytes()[0] as char
+            ]
+        );
+        assert_eq!(NavalIndicator::from_groups(&groups, &table), indicator);
+    }
+
+    #[test]
+    fn naval_message_end_to_end() {
+        let table = table();
+        let book = Kenngruppenbuch::from_file(Path::new(KENNGRUPPENBUCH_PATH));
+        let mut random = Random::new(9);
+        let indicator = book.choose(&mut random);
+        assert_ne!(indicator.kenngruppe, indicator.spruchschluessel);
+        let plaintext = "UBOOTSICHTETQUADRATANNAX";
+
+        let message = encrypt_naval(
+            &mut enigma(),
+            &indicator,
+            random_fillers(&mut random),
+            &table,
+            plaintext,
+        );
+        assert_ne!(message.text, plaintext);
+
+        let (received, text) = decrypt_naval(&mut enigma(), &message, &table);
+        assert_eq!(received, indicator);
+        assert_eq!(text, plaintext);
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_trigram() {
+        NavalIndicator::new("KJ", "BNM");
+    }
+}
@@ -0,0 +1,125 @@
+use crate::alphabet::{letters_from_positions, NUMBER_LETTERS_IN_ALPHABET};
+use crate::enigma::Enigma;
+use crate::message::{Indicator, Message};
+use crate::preamble::Part;
+use crate::random::Random;
+
+/// Operators had to split messages longer than this.
+pub const MAX_PART_LETTERS: usize = 250;
+
+/// One part of a split message, with a message key of its own.
+pub struct MessagePart {
+    pub part: Part,
+    pub message: Message,
+}
+
+/// Splits `text` into as few parts of at most `max_letters` as possible, all about equally long.
+pub fn split_text(text: &str, max_letters: usize) -> Vec<String> {
+    assert!(max_letters > 0, "parts must hold at least one letter");
+    let chars: Vec<char> = text.chars().collect();
+    let count = chars.len().div_ceil(max_letters).max(1);
+    let length = chars.len().div_ceil(count).max(1);
+    chars
+        .chunks(length)
+        .map(|part| part.iter().collect())
+        .chain(chars.is_empty().then(String::new))
+        .collect()
+}
+
+/// Enciphers every part with a random message key, starting each at the Grundstellung `enigma`
+/// is set to.
+pub fn encrypt_parts(
+    enigma: &Enigma,
+    text: &str,
+    max_letters: usize,
+    random: &mut Random,
+) -> Vec<MessagePart> {
+    let texts = split_text(text, max_letters);
+    let total = texts.len();
+    texts
+        .into_iter()
+        .enumerate()
+        .map(|(i, text)| {
+            let key =
+                letters_from_positions(&[0; 3].map(|_| random.below(NUMBER_LETTERS_IN_ALPHABET)));
+            let message = enigma
+                .clone()
+                .encrypt(Message::new(Indicator::new(key), text));
+            MessagePart {
+                part: Part {
+                    number: i + 1,
+                    total,
+                },
+                message,
+            }
+        })
+        .collect()
+}
+
+/// Deciphers the parts in the order of their numbers and joins the texts. All parts must be
+/// present.
+pub fn decrypt_parts(enigma: &Enigma, mut parts: Vec<MessagePart>) -> String {
+    parts.sort_by_key(|part| part.part.number);
+    for (i, part) in parts.iter().enumerate() {
+        assert_eq!(part.part.number, i + 1, "part {} is missing", i + 1);
+        assert_eq!(part.part.total, parts.len(), "parts of different messages");
+    }
+    parts
+        .into_iter()
+        .map(|part| enigma.clone().decrypt(part.message).text)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::preprocess_for_enigma;
+    use crate::message::TEXT;
+    use crate::parts::*;
+    use crate::rotorassembly::RotorAssembly;
+    use crate::{plugboard, Plugboard};
+    use std::path::Path;
+
+    fn enigma() -> Enigma {
+        let plugboard = Plugboard::from_file(Path::new(plugboard::PATH));
+        let mut enigma = Enigma::new(RotorAssembly::new_default(), plugboard);
+        enigma.set_positions([5, 17, 2]);
+        enigma
+    }
+
+    #[test]
+    fn split_evenly() {
+        let lengths = |text: &str, max| {
+            split_text(text, max)
+                .iter()
+                .map(String::len)
+                .collect::<Vec<usize>>()
+        };
+        assert_eq!(lengths(&"A".repeat(10), 4), vec![4, 4, 2]);
+        assert_eq!(lengths(&"A".repeat(501), 250), vec![167, 167, 167]);
+        assert_eq!(lengths(&"A".repeat(250), 250), vec![250]);
+        assert_eq!(lengths("", 250), vec![0]);
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_parts() {
+        let text = preprocess_for_enigma(TEXT).repeat(3);
+        let mut parts = encrypt_parts(&enigma(), &text, 100, &mut Random::new(45));
+        assert_eq!(parts.len(), text.len().div_ceil(100));
+        assert!(parts.iter().all(|part| part.message.text.len() <= 100));
+        assert_ne!(
+            parts[0].message.indicator.get_first_triplet(),
+            parts[1].message.indicator.get_first_triplet()
+        );
+        parts.reverse();
+        assert_eq!(decrypt_parts(&enigma(), parts), text);
+    }
+
+    #[test]
+    #[should_panic]
+    fn missing_part() {
+        let text = "A".repeat(300);
+        let mut parts = encrypt_parts(&enigma(), &text, 100, &mut Random::new(45));
+        parts.remove(1);
+        decrypt_parts(&enigma(), parts);
+    }
+}
@@ -1,12 +1,16 @@
-use crate::alphabet::is_capital_letter;
+use crate::alphabet::{is_capital_letter, to_index, ALPHABET};
+use crate::rotor::Table;
 use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
 use std::fs;
 use std::path::Path;
 
+#[derive(Clone)]
 pub struct Plugboard {
     pairs: Vec<Pair>,
 }
 
+#[derive(Clone)]
 pub struct Pair {
     char0: char,
     char1: char,
@@ -25,6 +29,10 @@ impl Pair {
         }
     }
 
+    pub fn chars(&self) -> [char; 2] {
+        [self.char0, self.char1]
+    }
+
     pub fn new(char0: char, char1: char) -> Self {
         assert_ne!(char0, char1, "chars cannot be equal");
         Self::assert_capital_letter(char0);
@@ -39,6 +47,7 @@ impl Pair {
 }
 
 pub(crate) const PATH: &str = "src/plugboard.txt";
+pub const MAX_PAIRS: usize = 10;
 
 impl Plugboard {
     pub fn from_file(path: &Path) -> Plugboard {
@@ -63,7 +72,7 @@ impl Plugboard {
     }
 
     fn sanity_check(items: &[Pair]) {
-        assert!(items.len() <= 10);
+        assert!(items.len() <= MAX_PAIRS);
 
         let mut hs = HashSet::new();
         for pair in items {
@@ -77,6 +86,11 @@ impl Plugboard {
         );
     }
 
+    /// Where each letter is sent, by letter index.
+    pub fn table(&self) -> Table {
+        ALPHABET.map(|char| to_index(self.encode_char(char)))
+    }
+
     pub fn encode_char(&self, input: char) -> char {
         for pair in &self.pairs {
             if pair.contains(input) {
@@ -88,6 +102,30 @@ impl Plugboard {
 
     pub fn add_pair(&mut self, pair: Pair) {
         self.pairs.push(pair);
+        Self::sanity_check(&self.pairs);
+    }
+
+    pub fn remove_pair(&mut self, index: usize) -> Pair {
+        self.pairs.remove(index)
+    }
+
+    pub fn pairs(&self) -> &[Pair] {
+        &self.pairs
+    }
+
+    pub fn is_plugged(&self, input: char) -> bool {
+        self.pairs.iter().any(|pair| pair.contains(input))
+    }
+}
+
+impl Display for Plugboard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let pairs: Vec<String> = self
+            .pairs
+            .iter()
+            .map(|pair| format!("{}{}", pair.char0, pair.char1))
+            .collect();
+        f.write_str(&pairs.join(" "))
     }
 }
 
@@ -128,6 +166,24 @@ mod tests {
         assert!(!pair.contains('C'));
     }
 
+    #[test]
+    fn add_and_remove_pairs() {
+        let mut plugboard = Plugboard::new(vec![Pair::new('A', 'B')]);
+        plugboard.add_pair(Pair::new('C', 'D'));
+        assert!(plugboard.is_plugged('D'));
+        assert_eq!(plugboard.to_string(), "AB CD");
+        assert_eq!(plugboard.remove_pair(0).chars(), ['A', 'B']);
+        assert!(!plugboard.is_plugged('A'));
+        assert_eq!(plugboard.pairs().len(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_pair_with_plugged_char() {
+        let mut plugboard = Plugboard::new(vec![Pair::new('A', 'B')]);
+        plugboard.add_pair(Pair::new('B', 'C'));
+    }
+
     fn test_char(plugboard: &Plugboard, input: char, expected: char) {
         assert_eq!(plugboard.encode_char(input), expected);
     }
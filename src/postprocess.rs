@@ -0,0 +1,259 @@
+use crate::crib::NUMBER_SPELLINGS;
+use crate::message::data_lines;
+use crate::profile::{Names, Numbers, Profile, NUMBER_ROW};
+use std::collections::HashMap;
+use std::path::Path;
+
+pub const PATH: &str = "src/words.txt";
+
+const UNKNOWN_LETTER_SCORE: i64 = -4;
+const FULL_STOP_SCORE: i64 = 1;
+/// Names are only recognised from this many doubled letters on.
+const MINIMUM_NAME_LENGTH: usize = 3;
+/// Longest number written with the top row of the keyboard.
+const MAXIMUM_NUMBER_LENGTH: usize = 12;
+
+/// Words as they should be printed, German and English.
+pub struct WordList {
+    words: Vec<String>,
+}
+
+/// Readable words, keyed by how a profile writes them.
+struct Forms {
+    words: HashMap<String, String>,
+    numbers: Vec<(String, char)>,
+    longest: usize,
+}
+
+enum Token {
+    Word(String),
+    Name(String),
+    Digits(String),
+    FullStop,
+    Unknown(char),
+}
+
+impl WordList {
+    /// Reads one word per line, see [`data_lines`].
+    pub fn from_file(path: &Path) -> WordList {
+        WordList {
+            words: data_lines(path),
+        }
+    }
+}
+
+impl Forms {
+    fn new(profile: &Profile, words: &WordList) -> Forms {
+        let mut forms = HashMap::new();
+        for word in &words.words {
+            forms.entry(profile.apply(word)).or_insert(word.clone());
+        }
+        let numbers: Vec<(String, char)> = NUMBER_SPELLINGS
+            .iter()
+            .enumerate()
+            .flat_map(|(digit, spellings)| {
+                spellings.iter().map(move |spelling| {
+                    (
+                        profile.apply(spelling),
+                        char::from_digit(digit as u32, 10).unwrap(),
+                    )
+                })
+            })
+            .collect();
+        let longest = forms
+            .keys()
+            .chain(numbers.iter().map(|(form, _)| form))
+            .map(String::len)
+            .max()
+            .unwrap_or(0);
+        Forms {
+            words: forms,
+            numbers,
+            longest,
+        }
+    }
+}
+
+/// Reverses the conventions of `profile` as far as possible and splits the letters into the
+/// words of `words`. Letters that are not part of any word stay in capitals.
+pub fn restore(text: &str, profile: &Profile, words: &WordList) -> String {
+    let forms = Forms::new(profile, words);
+    let chars: Vec<char> = text.chars().collect();
+    // best[i] is the best segmentation of the first i letters
+    let mut best: Vec<Option<(i64, usize, Token)>> = (0..=chars.len()).map(|_| None).collect();
+    let mut scores = vec![i64::MIN; chars.len() + 1];
+    scores[0] = 0;
+    for start in 0..chars.len() {
+        if scores[start] == i64::MIN {
+            continue;
+        }
+        for (end, score, token) in tokens_at(&chars, start, profile, &forms) {
+            let total = scores[start] + score;
+            if total > scores[end] {
+                scores[end] = total;
+                best[end] = Some((total, start, token));
+            }
+        }
+    }
+    let mut tokens = Vec::new();
+    let mut end = chars.len();
+    while end > 0 {
+        let (_, start, token) = best[end].take().unwrap();
+        tokens.push(token);
+        end = start;
+    }
+    tokens.reverse();
+    render(&tokens)
+}
+
+/// Every token that could start at `start`, with its end and score.
+fn tokens_at(
+    chars: &[char],
+    start: usize,
+    profile: &Profile,
+    forms: &Forms,
+) -> Vec<(usize, i64, Token)> {
+    let mut result = vec![(
+        start + 1,
+        UNKNOWN_LETTER_SCORE,
+        Token::Unknown(chars[start]),
+    )];
+    if profile.full_stop == Some(chars[start]) {
+        result.push((start + 1, FULL_STOP_SCORE, Token::FullStop));
+    }
+    for end in start + 1..=chars.len().min(start + forms.longest) {
+        let form: String = chars[start..end].iter().collect();
+        let score = ((end - start) * (end - start)) as i64;
+        if let Some(word) = forms.words.get(&form) {
+            result.push((end, score, Token::Word(word.clone())));
+        } else if let Some((_, digit)) = forms.numbers.iter().find(|(number, _)| *number == form) {
+            result.push((end, score, Token::Digits(digit.to_string())));
+        }
+    }
+    if profile.numbers == Numbers::NumberRow && chars[start] == profile.separator {
+        let digits: String = chars[start + 1..]
+            .iter()
+            .take(MAXIMUM_NUMBER_LENGTH)
+            .map_while(|c| NUMBER_ROW.iter().position(|row| row == c))
+            .map(|digit| char::from_digit(digit as u32, 10).unwrap())
+            .collect();
+        let end = start + digits.len() + 2;
+        if !digits.is_empty() && chars.get(end - 1) == Some(&profile.separator) {
+            let score = ((end - start) * (end - start)) as i64;
+            result.push((end, score, Token::Digits(digits)));
+        }
+    }
+    if profile.names == Names::DoubledLetters {
+        let pairs = chars[start..]
+            .chunks_exact(2)
+            .take_while(|pair| pair[0] == pair[1])
+            .count();
+        if pairs >= MINIMUM_NAME_LENGTH {
+            let mut name: String = chars[start..start + 2 * pairs].iter().step_by(2).collect();
+            if profile.ch_to_q {
+                name = restore_ch(&name);
+            }
+            result.push((start + 2 * pairs, (pairs * pairs) as i64, Token::Name(name)));
+        }
+    }
+    result
+}
+
+/// `Q` stands for `CH` unless it is followed by `U`.
+fn restore_ch(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        if *c == 'Q' && chars.get(i + 1) != Some(&'U') {
+            result.push_str("CH");
+        } else {
+            result.push(*c);
+        }
+    }
+    result
+}
+
+fn render(tokens: &[Token]) -> String {
+    let mut result = String::new();
+    let mut sentence_start = true;
+    let mut after_digits = false;
+    let mut after_unknown = false;
+    for token in tokens {
+        let (text, digits, unknown) = match token {
+            Token::FullStop => {
+                result.push('.');
+                sentence_start = true;
+                after_digits = false;
+                after_unknown = false;
+                continue;
+            }
+            Token::Word(word) if sentence_start => (capitalize(word), false, false),
+            Token::Word(word) => (word.clone(), false, false),
+            Token::Name(name) => (capitalize(&name.to_lowercase()), false, false),
+            Token::Digits(digits) => (digits.clone(), true, false),
+            Token::Unknown(c) => (c.to_string(), false, true),
+        };
+        let glued = (digits && after_digits) || (unknown && after_unknown);
+        if !result.is_empty() && !glued {
+            result.push(' ');
+        }
+        result.push_str(&text);
+        sentence_start = false;
+        after_digits = digits;
+        after_unknown = unknown;
+    }
+    result
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::TEXT;
+    use crate::postprocess::*;
+    use crate::profile::{ARMY, PLAIN, SPELLED};
+
+    fn words() -> WordList {
+        WordList::from_file(Path::new(PATH))
+    }
+
+    #[test]
+    fn restores_spelled_text() {
+        assert_eq!(
+            restore(&SPELLED.apply(TEXT), &SPELLED, &words()),
+            "Wetterbericht 0600. Wind 030 Stärke 4. Leichter Regen. Bedeckt. \
+            Gelegentlicher Nebel. 3 Liter."
+        );
+    }
+
+    #[test]
+    fn restores_army_text() {
+        let text = "Brücke bei *Orscha* um 1530 gesprengt.";
+        assert_eq!(
+            restore(&ARMY.apply(text), &ARMY, &words()),
+            "Brücke bei Orscha um 1530 gesprengt."
+        );
+    }
+
+    #[test]
+    fn unknown_letters_stay_together() {
+        assert_eq!(
+            restore("FEINDBEIKRTZYDORF", &PLAIN, &words()),
+            "Feind bei KRTZY Dorf"
+        );
+    }
+
+    #[test]
+    fn english() {
+        assert_eq!(
+            restore("ENEMYCONVOYATTACKED", &PLAIN, &words()),
+            "Enemy convoy attacked"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use crate::alphabet::is_capital_letter;
+use crate::message::{Indicator, Message, GROUPS};
+use crate::mode::{Mode, Procedure};
+use std::fmt::{Display, Formatter};
+
+const KENNGRUPPE_GROUP_LENGTH: usize = 5;
+
+/// Spruchkopf of an army or air force radio message, e.g.
+/// `U6Z DE C 1510 = 2TLE 1TL = 49 = EHZ TBS =`.
+pub struct Preamble {
+    pub receiver: String,
+    pub sender: String,
+    /// Time of origin as `HHMM`.
+    pub time_of_origin: String,
+    pub part: Option<Part>,
+    /// Letters of the text, including the Kenngruppe group.
+    pub letters: usize,
+    /// Start position in clear followed by the enciphered message key.
+    pub indicator: Indicator,
+}
+
+/// Part number of a message that was split, `2TLE 1TL` is the first of two parts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Part {
+    pub number: usize,
+    pub total: usize,
+}
+
+/// A message as written down by the intercept operator, the preamble followed by five-letter
+/// groups of which the first hides the Kenngruppe behind two filler letters.
+pub struct Transmission {
+    pub preamble: Preamble,
+    pub kenngruppe_group: String,
+    pub text: String,
+}
+
+impl Transmission {
+    pub fn new(preamble: Preamble, kenngruppe_group: String, text: String) -> Transmission {
+        assert!(
+            kenngruppe_group.len() == KENNGRUPPE_GROUP_LENGTH
+                && kenngruppe_group.chars().all(is_capital_letter),
+            "Kenngruppe group must have five capital letters"
+        );
+        assert!(
+            text.chars().all(is_capital_letter),
+            "text must only contain capital letters"
+        );
+        assert_eq!(
+            preamble.letters,
+            KENNGRUPPE_GROUP_LENGTH + text.len(),
+            "number of letters does not match the text"
+        );
+        Transmission {
+            preamble,
+            kenngruppe_group,
+            text,
+        }
+    }
+
+    /// Parses a transmission as written, fields of the preamble separated by `=` and the text in
+    /// groups after the last one. Whitespace and case do not matter, the part number is optional.
+    pub fn parse(transmission: &str) -> Transmission {
+        let transmission = transmission.to_uppercase();
+        let fields: Vec<&str> = transmission.split('=').map(str::trim).collect();
+        let [header, middle @ .., body] = fields.as_slice() else {
+            panic!("transmission must contain a preamble and a text");
+        };
+        let header: Vec<&str> = header.split_whitespace().collect();
+        let [receiver, "DE", sender, time_of_origin] = header.as_slice() else {
+            panic!("preamble must start with <receiver> DE <sender> <time>");
+        };
+        assert!(
+            time_of_origin.len() == 4 && is_number(time_of_origin),
+            "time of origin must have four digits"
+        );
+
+        let mut total = None;
+        let mut number = None;
+        let mut letters = None;
+        let mut indicator = String::new();
+        for token in middle.iter().flat_map(|field| field.split_whitespace()) {
+            if let Some(value) = token.strip_suffix("TLE").filter(|value| is_number(value)) {
+                total = Some(parse_number(value));
+            } else if let Some(value) = token.strip_suffix("TL").filter(|value| is_number(value)) {
+                number = Some(parse_number(value));
+            } else if is_number(token) {
+                letters = Some(parse_number(token));
+            } else {
+                indicator.push_str(token);
+            }
+        }
+        let part = match (number, total) {
+            (Some(number), Some(total)) => Some(Part { number, total }),
+            (None, None) => None,
+            _ => panic!("part number needs both TLE and TL"),
+        };
+        let indicator = Indicator::new(indicator);
+        indicator.sanity_check_for(&Mode::Decrypt, Procedure::SingleKey);
+
+        let body: String = body.split_whitespace().collect();
+        assert!(
+            body.len() >= KENNGRUPPE_GROUP_LENGTH,
+            "text must start with the Kenngruppe group"
+        );
+        let (kenngruppe_group, text) = body.split_at(KENNGRUPPE_GROUP_LENGTH);
+        let preamble = Preamble {
+            receiver: receiver.to_string(),
+            sender: sender.to_string(),
+            time_of_origin: time_of_origin.to_string(),
+            part,
+            letters: letters.expect("preamble must contain the number of letters"),
+            indicator,
+        };
+        Transmission::new(preamble, kenngruppe_group.to_string(), text.to_string())
+    }
+
+    /// The Kenngruppe, the last three letters of the first group.
+    pub fn kenngruppe(&self) -> &str {
+        &self.kenngruppe_group[KENNGRUPPE_GROUP_LENGTH - 3..]
+    }
+
+    /// The enciphered message for the single key procedure, without the Kenngruppe group.
+    pub fn message(&self) -> Message {
+        let indicator = format!(
+            "{}{}",
+            self.preamble.indicator.get_first_triplet(),
+            self.preamble.indicator.get_second_triplet()
+        );
+        Message::new(Indicator::new(indicator), self.text.clone())
+    }
+}
+
+impl Display for Preamble {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} DE {} {} = ",
+            self.receiver, self.sender, self.time_of_origin
+        ))?;
+        if let Some(part) = self.part {
+            f.write_fmt(format_args!("{} = ", part))?;
+        }
+        f.write_fmt(format_args!(
+            "{} = {} {} =",
+            self.letters,
+            self.indicator.get_first_triplet(),
+            self.indicator.get_second_triplet()
+        ))
+    }
+}
+
+impl Display for Part {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}TLE {}TL", self.total, self.number))
+    }
+}
+
+impl Display for Transmission {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let body = format!("{}{}", self.kenngruppe_group, self.text);
+        f.write_fmt(format_args!("{}\n{}", self.preamble, GROUPS.format(&body)))
+    }
+}
+
+fn is_number(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_digit())
+}
+
+fn parse_number(value: &str) -> usize {
+    value.parse().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::preamble::*;
+
+    const TRANSMISSION: &str = "U6Z DE C 1510 = 2tle 1tl = 49 = EHZ TBS =
+        TVEXS QBLTW LDAHH YEOEF PTWYB LENDP MKOXL DFAMU DWIJD XRJZ";
+
+    #[test]
+    fn parse_transmission() {
+        let transmission = Transmission::parse(TRANSMISSION);
+        let preamble = &transmission.preamble;
+        assert_eq!(preamble.receiver, "U6Z");
+        assert_eq!(preamble.sender, "C");
+        assert_eq!(preamble.time_of_origin, "1510");
+        assert_eq!(
+            preamble.part,
+            Some(Part {
+                number: 1,
+                total: 2
+            })
+        );
+        assert_eq!(preamble.letters, 49);
+        assert_eq!(transmission.kenngruppe(), "EXS");
+        let message = transmission.message();
+        assert_eq!(message.indicator.get_first_triplet(), "EHZ");
+        assert_eq!(message.indicator.get_second_triplet(), "TBS");
+        assert_eq!(message.text.len(), 44);
+    }
+
+    #[test]
+    fn format_transmission() {
+        let formatted = Transmission::parse(TRANSMISSION).to_string();
+        assert_eq!(
+            formatted,
+            "U6Z DE C 1510 = 2TLE 1TL = 49 = EHZ TBS =\n\
+            TVEXS QBLTW LDAHH YEOEF PTWYB LENDP MKOXL DFAMU DWIJD XRJZ"
+        );
+        assert_eq!(Transmission::parse(&formatted).to_string(), formatted);
+    }
+
+    #[test]
+    fn parse_transmission_without_part() {
+        let transmission = Transmission::parse("P5K DE 4KZ 0915 = 7 = QRS KTL = ABXYZKL");
+        assert_eq!(transmission.preamble.part, None);
+        assert_eq!(transmission.kenngruppe(), "XYZ");
+    }
+
+    #[test]
+    #[should_panic]
+    fn wrong_number_of_letters() {
+        Transmission::parse("P5K DE 4KZ 0915 = 8 = QRS KTL = ABXYZKL");
+    }
+}
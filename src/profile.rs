@@ -0,0 +1,237 @@
+use crate::alphabet::{is_combining_mark, normalise};
+use crate::crib::NUMBER_SPELLINGS;
+use std::mem;
+
+/// Marks proper names in the plaintext, e.g. `*Kurtinowa*`.
+const NAME_MARK: char = '*';
+/// Written after the vowel in the decomposed form of `Ä`, `Ö` and `Ü`.
+const COMBINING_DIAERESIS: char = '\u{308}';
+/// Digits in the order of the top row of the keyboard, `Q` is 1 and `P` is 0.
+pub const NUMBER_ROW: [char; 10] = ['P', 'Q', 'W', 'E', 'R', 'T', 'Z', 'U', 'I', 'O'];
+
+/// How digits are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Numbers {
+    Drop,
+    /// Every digit spelled out, `0600` becomes `NULLSEQSNULLNULL` with `CH` to `Q`.
+    SpellOut,
+    /// Letters of the top row between separators, `0600` becomes `YPZPPY`.
+    NumberRow,
+}
+
+/// How proper names marked with `*` are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Names {
+    Unchanged,
+    /// Every letter doubled, `OTTO` becomes `OOTTTTOO`.
+    DoubledLetters,
+}
+
+/// Conventions for turning plaintext into letters that can be enciphered. Other letters are
+/// normalised, see [`normalise`], anything else a profile has no rule for is dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Profile {
+    /// `Ä`, `Ö` and `Ü` become `AE`, `OE` and `UE` instead of their base letters.
+    pub transliterate_umlauts: bool,
+    /// Letter written for a full stop.
+    pub full_stop: Option<char>,
+    pub numbers: Numbers,
+    pub names: Names,
+    /// `CH` is shortened to `Q` within words.
+    pub ch_to_q: bool,
+    /// Separates numbers from the text.
+    pub separator: char,
+}
+
+/// Only keeps the letters.
+pub const PLAIN: Profile = Profile {
+    transliterate_umlauts: false,
+    full_stop: None,
+    numbers: Numbers::Drop,
+    names: Names::Unchanged,
+    ch_to_q: false,
+    separator: 'Y',
+};
+
+/// The rules of the army and air force.
+pub const ARMY: Profile = Profile {
+    transliterate_umlauts: true,
+    full_stop: Some('X'),
+    numbers: Numbers::NumberRow,
+    names: Names::DoubledLetters,
+    ch_to_q: true,
+    separator: 'Y',
+};
+
+/// Like [`ARMY`], but with the digits spelled out.
+pub const SPELLED: Profile = Profile {
+    numbers: Numbers::SpellOut,
+    ..ARMY
+};
+
+impl Profile {
+    pub fn parse(name: &str) -> Option<Profile> {
+        match name {
+            "plain" => Some(PLAIN),
+            "army" => Some(ARMY),
+            "spelled" => Some(SPELLED),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut result = String::new();
+        // letters of the current word, the rules for words only apply within it
+        let mut word = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            if c == COMBINING_DIAERESIS
+                && self.transliterate_umlauts
+                && i > 0
+                && "aouAOU".contains(chars[i - 1])
+            {
+                // the decomposed form of an umlaut
+                word.push('E');
+                i += 1;
+                continue;
+            }
+            if normalise(c).is_some() || is_combining_mark(c) {
+                self.push_char(&mut word, c);
+                i += 1;
+                continue;
+            }
+            self.push_word(&mut result, &mem::take(&mut word));
+            if c == NAME_MARK {
+                // a mark that is not closed has no rule and is dropped
+                match chars[i + 1..].iter().position(|c| *c == NAME_MARK) {
+                    Some(length) => {
+                        let name: String = chars[i + 1..i + 1 + length].iter().collect();
+                        self.push_name(&mut result, &name);
+                        i += length + 2;
+                    }
+                    None => i += 1,
+                }
+            } else if c.is_ascii_digit() {
+                let length = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+                self.push_number(&mut result, &chars[i..i + length]);
+                i += length;
+            } else {
+                self.push_char(&mut result, c);
+                i += 1;
+            }
+        }
+        self.push_word(&mut result, &word);
+        result
+    }
+
+    fn push_word(&self, result: &mut String, word: &str) {
+        if self.ch_to_q {
+            result.push_str(&word.replace("CH", "Q"));
+        } else {
+            result.push_str(word);
+        }
+    }
+
+    fn push_name(&self, result: &mut String, name: &str) {
+        let letters = Profile {
+            names: Names::Unchanged,
+            ..*self
+        }
+        .apply(name);
+        match self.names {
+            Names::Unchanged => result.push_str(&letters),
+            Names::DoubledLetters => {
+                for c in letters.chars() {
+                    result.push(c);
+                    result.push(c);
+                }
+            }
+        }
+    }
+
+    fn push_number(&self, result: &mut String, digits: &[char]) {
+        let digits = digits.iter().map(|c| c.to_digit(10).unwrap() as usize);
+        match self.numbers {
+            Numbers::Drop => {}
+            Numbers::SpellOut => {
+                for digit in digits {
+                    self.push_word(result, NUMBER_SPELLINGS[digit][0]);
+                }
+            }
+            Numbers::NumberRow => {
+                result.push(self.separator);
+                result.extend(digits.map(|digit| NUMBER_ROW[digit]));
+                result.push(self.separator);
+            }
+        }
+    }
+
+    fn push_char(&self, result: &mut String, c: char) {
+        match c {
+            '.' => result.extend(self.full_stop),
+            'ä' | 'Ä' if self.transliterate_umlauts => result.push_str("AE"),
+            'ö' | 'Ö' if self.transliterate_umlauts => result.push_str("OE"),
+            'ü' | 'Ü' if self.transliterate_umlauts => result.push_str("UE"),
+            _ => result.extend(normalise(c)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::{preprocess_for_enigma, TEXT};
+    use crate::profile::*;
+
+    #[test]
+    fn plain_only_keeps_letters() {
+        assert_eq!(PLAIN.apply("Größe 12. *Otto*"), "GROSSEOTTO");
+        assert_eq!(PLAIN.apply(TEXT), preprocess_for_enigma(TEXT));
+    }
+
+    #[test]
+    fn army_conventions() {
+        assert_eq!(ARMY.apply("Brücke gesprengt."), "BRUECKEGESPRENGTX");
+        assert_eq!(ARMY.apply("Nacht"), "NAQT");
+        assert_eq!(ARMY.apply("Straße"), "STRASSE");
+        assert_eq!(ARMY.apply("um 0600 Uhr"), "UMYPZPPYUHR");
+        assert_eq!(ARMY.apply("an *Otto*"), "ANOOTTTTOO");
+    }
+
+    #[test]
+    fn ch_to_q_within_words() {
+        assert_eq!(ARMY.apply("Bac Haus"), "BACHAUS");
+        assert_eq!(ARMY.apply("*Bach*"), "BBAAQQ");
+        assert_eq!(ARMY.apply("Lache\u{301}"), "LAQE");
+        assert_eq!(ARMY.apply("Bru\u{308}cke"), "BRUECKE");
+        assert_eq!(PLAIN.apply("Bru\u{308}cke"), "BRUCKE");
+        let profile = Profile {
+            separator: 'C',
+            ..ARMY
+        };
+        assert_eq!(profile.apply("1 Haus"), "CQCHAUS");
+    }
+
+    #[test]
+    fn spelled_numbers() {
+        assert_eq!(SPELLED.apply("0600"), "NULLSEQSNULLNULL");
+        assert_eq!(SPELLED.apply("Wind 2"), "WINDZWO");
+    }
+
+    #[test]
+    fn configurable() {
+        let profile = Profile {
+            full_stop: None,
+            ch_to_q: false,
+            ..ARMY
+        };
+        assert_eq!(profile.apply("Nacht."), "NACHT");
+    }
+
+    #[test]
+    fn unclosed_name() {
+        assert_eq!(ARMY.apply("an *Otto"), "ANOTTO");
+        assert_eq!(ARMY.apply("5 * 3"), "YTYYEY");
+    }
+}
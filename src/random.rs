@@ -0,0 +1,67 @@
+const SEED_MASK: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Small xorshift64* generator, good enough to randomise searches and reproducible by seed.
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // the state must never be zero, xorshift would only ever return zero
+        let state = seed ^ SEED_MASK;
+        Random {
+            state: if state == 0 { 1 } else { state },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in `0..bound`, apart from a negligible modulo bias.
+    pub fn below(&mut self, bound: usize) -> usize {
+        assert!(bound > 0);
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::random::*;
+
+    #[test]
+    fn reproducible_by_seed() {
+        let mut a = Random::new(7);
+        let mut b = Random::new(7);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Random::new(7).next_u64(), Random::new(8).next_u64());
+    }
+
+    #[test]
+    fn seed_that_cancels_the_mask() {
+        let mut random = Random::new(SEED_MASK);
+        assert_ne!(random.next_u64(), 0);
+        assert_ne!(random.next_u64(), random.next_u64());
+    }
+
+    #[test]
+    fn below_covers_range() {
+        let mut random = Random::new(0);
+        let mut seen = [false; 26];
+        for _ in 0..1000 {
+            seen[random.below(26)] = true;
+        }
+        assert!(seen.iter().all(|seen| *seen));
+    }
+}
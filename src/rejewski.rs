@@ -0,0 +1,255 @@
+use crate::alphabet::{get_position_in_alphabet, NUMBER_LETTERS_IN_ALPHABET};
+use crate::catalog::{Setting, SettingLookup};
+use crate::message::Indicator;
+use crate::rotorassembly::RotorAssembly;
+use std::fmt::{Display, Formatter};
+
+const TRIPLET_LENGTH: usize = 3;
+
+/// Maps the position of each letter in the alphabet to the position of its image.
+pub type Permutation = [usize; NUMBER_LETTERS_IN_ALPHABET];
+
+/// Cycle lengths of the AD, BE and CF permutations, each sorted in descending order.
+///
+/// The cycle structure does not depend on the plugboard, which only conjugates the permutations.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Characteristic {
+    cycles: [Vec<usize>; TRIPLET_LENGTH],
+}
+
+impl Characteristic {
+    /// Returns `None` if the indicators are contradictory or do not cover every letter in each
+    /// of the three permutations. A few dozen indicators are usually enough.
+    pub fn from_indicators(indicators: &[Indicator]) -> Option<Characteristic> {
+        let permutations = indicator_permutations(indicators)?;
+        Some(Self::from_permutations(&permutations))
+    }
+
+    /// The characteristic of a day on which the indicators were enciphered at `positions`.
+    pub fn from_assembly(assembly: &mut RotorAssembly, positions: [usize; 3]) -> Characteristic {
+        let mut scramblers = [[0; NUMBER_LETTERS_IN_ALPHABET]; 2 * TRIPLET_LENGTH];
+        assembly.set_positions(positions);
+        for scrambler in &mut scramblers {
+            assembly.increment_cypher_rotor_positions();
+            for (input, output) in scrambler.iter_mut().enumerate() {
+                *output = assembly.encode_index_without_stepping(input as u8) as usize;
+            }
+        }
+        let products = [0, 1, 2].map(|i| compose(&scramblers[i], &scramblers[i + TRIPLET_LENGTH]));
+        Self::from_permutations(&products)
+    }
+
+    fn from_permutations(permutations: &[Permutation; TRIPLET_LENGTH]) -> Characteristic {
+        Characteristic {
+            cycles: permutations.map(|p| cycle_lengths(&p)),
+        }
+    }
+
+    /// Compact form used to index the catalog, e.g. `13,13/13,13/10,10,3,3`.
+    pub fn signature(&self) -> String {
+        let parts: Vec<String> = self
+            .cycles
+            .iter()
+            .map(|lengths| {
+                let lengths: Vec<String> = lengths.iter().map(usize::to_string).collect();
+                lengths.join(",")
+            })
+            .collect();
+        parts.join("/")
+    }
+
+    pub fn from_signature(signature: &str) -> Characteristic {
+        let parts: Vec<Vec<usize>> = signature
+            .split('/')
+            .map(|part| {
+                part.split(',')
+                    .map(|length| length.parse().expect("cycle length must be a number"))
+                    .collect()
+            })
+            .collect();
+        let cycles: [Vec<usize>; TRIPLET_LENGTH] = parts
+            .try_into()
+            .expect("signature must consist of three permutations");
+        for lengths in &cycles {
+            assert_eq!(
+                lengths.iter().sum::<usize>(),
+                NUMBER_LETTERS_IN_ALPHABET,
+                "cycle lengths must add up to the length of the alphabet"
+            );
+        }
+        Characteristic { cycles }
+    }
+}
+
+impl Display for Characteristic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, (name, lengths)) in ["AD", "BE", "CF"].iter().zip(&self.cycles).enumerate() {
+            if i > 0 {
+                f.write_str("  ")?;
+            }
+            let lengths: Vec<String> = lengths.iter().map(usize::to_string).collect();
+            f.write_fmt(format_args!("{}: {}", name, lengths.join(" ")))?;
+        }
+        Ok(())
+    }
+}
+
+/// Recovers the candidate wheel orders and Grundstellungen of a day from its doubled indicators.
+/// Returns `None` if the indicators are not sufficient to build the characteristic.
+pub fn characteristic_attack(
+    indicators: &[Indicator],
+    catalog: &impl SettingLookup,
+) -> Option<(Characteristic, Vec<Setting>)> {
+    let characteristic = Characteristic::from_indicators(indicators)?;
+    let settings = catalog.lookup(&characteristic);
+    Some((characteristic, settings))
+}
+
+/// Builds the AD, BE and CF permutations, which map the n-th letter of each indicator to the
+/// (n+3)-th letter.
+pub fn indicator_permutations(indicators: &[Indicator]) -> Option<[Permutation; TRIPLET_LENGTH]> {
+    let mut partial = [[None; NUMBER_LETTERS_IN_ALPHABET]; TRIPLET_LENGTH];
+    for indicator in indicators {
+        let first = indicator.get_first_triplet().chars();
+        let second = indicator.get_second_triplet().chars();
+        for (mapping, (from, to)) in partial.iter_mut().zip(first.zip(second)) {
+            let from = get_position_in_alphabet(from);
+            let to = get_position_in_alphabet(to);
+            match mapping[from] {
+                None => mapping[from] = Some(to),
+                Some(existing) if existing == to => {}
+                Some(_) => return None,
+            }
+        }
+    }
+    let mut result = [[0; NUMBER_LETTERS_IN_ALPHABET]; TRIPLET_LENGTH];
+    for (permutation, mapping) in result.iter_mut().zip(partial) {
+        for (output, image) in permutation.iter_mut().zip(mapping) {
+            *output = image?;
+        }
+    }
+    Some(result)
+}
+
+/// The permutation that applies `first`, then `second`.
+pub fn compose(first: &Permutation, second: &Permutation) -> Permutation {
+    first.map(|image| second[image])
+}
+
+/// Lengths of all cycles of `permutation`, sorted in descending order.
+pub fn cycle_lengths(permutation: &Permutation) -> Vec<usize> {
+    let mut visited = [false; NUMBER_LETTERS_IN_ALPHABET];
+    let mut result = Vec::new();
+    for start in 0..NUMBER_LETTERS_IN_ALPHABET {
+        let mut length = 0;
+        let mut current = start;
+        while !visited[current] {
+            visited[current] = true;
+            current = permutation[current];
+            length += 1;
+        }
+        if length > 0 {
+            result.push(length);
+        }
+    }
+    result.sort_unstable_by(|a, b| b.cmp(a));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::alphabet::{ALPHABET, NUMBER_LETTERS_IN_ALPHABET};
+    use crate::catalog::Catalog;
+    use crate::message::{Indicator, Message};
+    use crate::rejewski::*;
+    use crate::rotor::PATH;
+    use crate::{plugboard, Enigma, Plugboard};
+    use std::path::Path;
+
+    const GRUNDSTELLUNG: [usize; 3] = [3, 14, 22];
+
+    fn day_of_indicators() -> Vec<Indicator> {
+        let plugboard = Plugboard::from_file(Path::new(plugboard::PATH));
+        let assembly = RotorAssembly::new_default();
+        let mut enigma = Enigma::new(assembly, plugboard);
+        let mut result = Vec::new();
+        for i in 0..NUMBER_LETTERS_IN_ALPHABET {
+            let key: String = [i, 3 * i + 1, 7 * i + 2]
+                .iter()
+                .map(|p| ALPHABET[p % NUMBER_LETTERS_IN_ALPHABET])
+                .collect();
+            enigma.set_positions(GRUNDSTELLUNG);
+            let message = enigma.encrypt(Message::new(Indicator::new(key), String::new()));
+            result.push(message.indicator);
+        }
+        result
+    }
+
+    #[test]
+    fn test_cycle_lengths() {
+        let mut identity = [0; NUMBER_LETTERS_IN_ALPHABET];
+        for (i, image) in identity.iter_mut().enumerate() {
+            *image = i;
+        }
+        assert_eq!(
+            cycle_lengths(&identity),
+            vec![1; NUMBER_LETTERS_IN_ALPHABET]
+        );
+
+        let mut shifted = identity;
+        shifted.rotate_left(1);
+        assert_eq!(cycle_lengths(&shifted), vec![NUMBER_LETTERS_IN_ALPHABET]);
+
+        let mut swapped = identity;
+        swapped.swap(0, 1);
+        swapped[2..5].rotate_left(1);
+        assert_eq!(cycle_lengths(&swapped)[0..3], [3, 2, 1]);
+    }
+
+    #[test]
+    fn characteristic_does_not_depend_on_plugboard() {
+        let from_indicators = Characteristic::from_indicators(&day_of_indicators()).unwrap();
+        let mut assembly = RotorAssembly::new_default();
+        let from_assembly = Characteristic::from_assembly(&mut assembly, GRUNDSTELLUNG);
+        assert_eq!(from_indicators, from_assembly);
+    }
+
+    #[test]
+    fn too_few_indicators() {
+        let indicators = day_of_indicators();
+        assert!(Characteristic::from_indicators(&indicators[0..3]).is_none());
+    }
+
+    #[test]
+    fn contradicting_indicators() {
+        let mut indicators = day_of_indicators();
+        indicators.push(Indicator::new("AAABBB".to_string()));
+        indicators.push(Indicator::new("AAACCC".to_string()));
+        assert!(Characteristic::from_indicators(&indicators).is_none());
+    }
+
+    #[test]
+    fn signature_round_trip() {
+        let mut assembly = RotorAssembly::new_default();
+        let characteristic = Characteristic::from_assembly(&mut assembly, GRUNDSTELLUNG);
+        let signature = characteristic.signature();
+        assert_eq!(Characteristic::from_signature(&signature), characteristic);
+    }
+
+    #[test]
+    #[should_panic]
+    fn signature_with_wrong_cycle_lengths() {
+        Characteristic::from_signature("13,12/13,13/13,13");
+    }
+
+    #[test]
+    fn recovers_grundstellung() {
+        let catalog = Catalog::generate(Path::new(PATH), &[["I", "II", "III"]], "B");
+        let (_, settings) = characteristic_attack(&day_of_indicators(), &catalog).unwrap();
+        let expected = Setting {
+            wheel_order: ["I", "II", "III"],
+            positions: GRUNDSTELLUNG,
+        };
+        assert!(settings.contains(&expected));
+    }
+}
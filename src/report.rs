@@ -0,0 +1,174 @@
+use crate::alphabet::letters_from_positions;
+use crate::cryptoattack::Key;
+use crate::plugboard::Plugboard;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Outcome of [`crate::cryptoattack::known_plaintext_attack`].
+pub struct AttackReport {
+    pub crib: String,
+    /// Offsets at which no letter of the crib encrypts to itself.
+    pub crib_offsets: Vec<usize>,
+    /// Best first.
+    pub candidates: Vec<KeyCandidate>,
+    pub timings: Vec<Timing>,
+}
+
+pub struct KeyCandidate {
+    pub crib_offset: usize,
+    pub key: Key,
+    pub plugboard: Plugboard,
+    pub score: f64,
+    /// Beginning of the decryption with this key.
+    pub preview: String,
+}
+
+pub struct Timing {
+    pub stage: &'static str,
+    pub duration: Duration,
+}
+
+impl AttackReport {
+    pub fn to_json(&self) -> String {
+        let offsets: Vec<String> = self.crib_offsets.iter().map(usize::to_string).collect();
+        let candidates: Vec<String> = self.candidates.iter().map(KeyCandidate::to_json).collect();
+        let timings: Vec<String> = self
+            .timings
+            .iter()
+            .map(|timing| {
+                format!(
+                    "{{\"stage\":{},\"seconds\":{}}}",
+                    json_string(timing.stage),
+                    timing.duration.as_secs_f64()
+                )
+            })
+            .collect();
+        format!(
+            "{{\"crib\":{},\"crib_offsets\":[{}],\"candidates\":[{}],\"timings\":[{}]}}",
+            json_string(&self.crib),
+            offsets.join(","),
+            candidates.join(","),
+            timings.join(",")
+        )
+    }
+}
+
+impl KeyCandidate {
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"crib_offset\":{},\"wheel_order\":{},\"reflector\":{},\
+             \"ring_settings\":{},\"positions\":{},\"plugboard\":{},\"score\":{},\"preview\":{}}}",
+            self.crib_offset,
+            json_string(&self.key.wheel_order.join("-")),
+            json_string(self.key.reflector),
+            json_string(&letters_from_positions(&self.key.ring_settings)),
+            json_string(&letters_from_positions(&self.key.positions)),
+            json_string(&self.plugboard.to_string()),
+            self.score,
+            json_string(&self.preview)
+        )
+    }
+}
+
+impl Display for AttackReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let offsets: Vec<String> = self.crib_offsets.iter().map(usize::to_string).collect();
+        writeln!(f, "crib: {}", self.crib)?;
+        writeln!(
+            f,
+            "possible offsets ({}): {}",
+            self.crib_offsets.len(),
+            offsets.join(" ")
+        )?;
+        writeln!(
+            f,
+            "{:>5}  {:>6}  {:<33}  {:<29}  preview",
+            "score", "offset", "key", "plugboard"
+        )?;
+        for candidate in &self.candidates {
+            writeln!(
+                f,
+                "{:>5.2}  {:>6}  {:<33}  {:<29}  {}",
+                candidate.score,
+                candidate.crib_offset,
+                candidate.key.to_string(),
+                candidate.plugboard.to_string(),
+                candidate.preview
+            )?;
+        }
+        let timings: Vec<String> = self
+            .timings
+            .iter()
+            .map(|timing| format!("{} {:.3}s", timing.stage, timing.duration.as_secs_f64()))
+            .collect();
+        write!(f, "timings: {}", timings.join(", "))
+    }
+}
+
+fn json_string(input: &str) -> String {
+    let mut result = String::from("\"");
+    for char in input.chars() {
+        match char {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plugboard::Pair;
+    use crate::report::*;
+
+    fn report() -> AttackReport {
+        AttackReport {
+            crib: "WETTER".to_string(),
+            crib_offsets: vec![0, 4],
+            candidates: vec![KeyCandidate {
+                crib_offset: 4,
+                key: Key {
+                    wheel_order: ["I", "II", "III"],
+                    reflector: "B",
+                    ring_settings: [0, 0, 0],
+                    positions: [16, 17, 18],
+                },
+                plugboard: Plugboard::new(vec![Pair::new('A', 'S')]),
+                score: 0.5,
+                preview: "XXXXWETTER".to_string(),
+            }],
+            timings: vec![Timing {
+                stage: "solve plugboard",
+                duration: Duration::from_millis(250),
+            }],
+        }
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            report().to_json(),
+            "{\"crib\":\"WETTER\",\"crib_offsets\":[0,4],\"candidates\":[{\"crib_offset\":4,\
+             \"wheel_order\":\"I-II-III\",\"reflector\":\"B\",\
+             \"ring_settings\":\"AAA\",\"positions\":\"QRS\",\
+             \"plugboard\":\"AS\",\"score\":0.5,\"preview\":\"XXXXWETTER\"}],\
+             \"timings\":[{\"stage\":\"solve plugboard\",\"seconds\":0.25}]}"
+        );
+    }
+
+    #[test]
+    fn json_string_escapes() {
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\u000a\"");
+    }
+
+    #[test]
+    fn table() {
+        let table = report().to_string();
+        assert!(table.contains("possible offsets (2): 0 4"));
+        assert!(table.contains("B I-II-III ring AAA position QRS"));
+        assert!(table.ends_with("timings: solve plugboard 0.250s"));
+    }
+}
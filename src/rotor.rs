@@ -1,11 +1,7 @@
-use crate::alphabet::{
-    get_position_in_alphabet, ALPHABET, ASCII_LETTER_A, NUMBER_LETTERS_IN_ALPHABET,
-};
+use crate::alphabet::{from_index, to_index, ALPHABET, ASCII_LETTER_A, NUMBER_LETTERS_IN_ALPHABET};
 use core::char;
-use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 use std::fs;
-use std::iter::zip;
 use std::path::Path;
 use std::str::Split;
 
@@ -13,35 +9,46 @@ pub const PATH: &str = "src/rotors.txt";
 const PLACEHOLDER: char = '_';
 const TURNOVER: char = 'T';
 
+/// Permutation of the alphabet by letter index.
+pub type Table = [u8; NUMBER_LETTERS_IN_ALPHABET];
+
+#[allow(dead_code)] // used in tests
 #[derive(PartialEq)]
 enum ShiftDirection {
     Forward,
     Reverse,
 }
 
+#[derive(Clone)]
 pub struct Rotor {
-    forward: HashMap<char, char>,
-    reverse: HashMap<char, char>,
-    mapping: Vec<char>,
-    turnover_position: usize,
+    /// Wiring for every offset, `forward[offset][input]`.
+    forward: [Table; NUMBER_LETTERS_IN_ALPHABET],
+    /// Inverse of `forward` for every offset.
+    reverse: [Table; NUMBER_LETTERS_IN_ALPHABET],
+    /// Rotors VI to VIII have two notches.
+    turnover_positions: Vec<usize>,
     position: usize,
+    ring_setting: usize,
     turnover_has_occurred: bool,
 }
 
+#[derive(Clone)]
 pub struct Reflector {
-    chars: Vec<char>,
+    table: Table,
 }
 
 impl Reflector {
     pub fn from_file(path: &Path, id: &str) -> Reflector {
         let items = get_items_from_file_for_id(path, id);
         assert_eq!(
-            items.1, PLACEHOLDER,
+            items.1,
+            PLACEHOLDER.to_string(),
             "{} {}",
-            "Found turnover char for reflector. Should have been", PLACEHOLDER
+            "Found turnover char for reflector. Should have been",
+            PLACEHOLDER
         );
         Reflector {
-            chars: mapping_to_vector(&items.0),
+            table: mapping_to_array(&items.0).map(to_index),
         }
     }
 }
@@ -54,44 +61,46 @@ impl Debug for Rotor {
             PLACEHOLDER
         };
         f.write_fmt(format_args!(
-            "{}/{} {}",
-            self.position, self.turnover_position, turnover
+            "{}/{:?} {}",
+            self.position, self.turnover_positions, turnover
         ))
     }
 }
 
 pub trait Encode {
-    fn encode_char(&mut self, input: char) -> char;
+    fn encode(&self, input: u8) -> u8;
+
+    #[allow(dead_code)] // used in tests
+    fn encode_char(&mut self, input: char) -> char {
+        from_index(self.encode(to_index(input)))
+    }
 }
 
 impl Encode for Rotor {
-    fn encode_char(&mut self, input: char) -> char {
-        *self
-            .forward
-            .get(&self.shift_char_by_position(input, ShiftDirection::Forward))
-            .unwrap()
+    fn encode(&self, input: u8) -> u8 {
+        self.encode_at(self.offset(), input)
     }
 }
 
 impl Encode for Reflector {
-    fn encode_char(&mut self, input: char) -> char {
-        let position_in_alphabet = get_position_in_alphabet(input);
-        self.chars[position_in_alphabet]
+    fn encode(&self, input: u8) -> u8 {
+        self.table[input as usize]
     }
 }
 
 impl Rotor {
-    pub fn new(mapping: &str, turnover_char: char) -> Rotor {
-        let chars: Vec<char> = mapping_to_vector(mapping);
-        let turnover_position = Self::find_turnover_position(turnover_char);
-        let forward = Self::generate_forward_map(&chars);
-        let reverse = Self::generate_reverse_map(&chars);
+    pub fn new(mapping: &str, turnover_chars: &str) -> Rotor {
+        let wiring = mapping_to_array(mapping).map(to_index);
+        let turnover_positions = turnover_chars
+            .chars()
+            .map(Self::find_turnover_position)
+            .collect();
         Rotor {
-            forward,
-            reverse,
-            mapping: chars,
-            turnover_position,
+            forward: Self::generate_forward_tables(&wiring),
+            reverse: Self::generate_reverse_tables(&wiring),
+            turnover_positions,
             position: 0,
+            ring_setting: 0,
             turnover_has_occurred: false,
         }
     }
@@ -101,22 +110,36 @@ impl Rotor {
         self.position
     }
 
-    fn generate_forward_map(vec: &[char]) -> HashMap<char, char> {
-        let mut result: HashMap<char, char> = HashMap::with_capacity(NUMBER_LETTERS_IN_ALPHABET);
-        for (input, output) in zip('A'..='Z', vec) {
-            result.insert(input, *output);
+    fn generate_forward_tables(wiring: &Table) -> [Table; NUMBER_LETTERS_IN_ALPHABET] {
+        let mut result = [[0; NUMBER_LETTERS_IN_ALPHABET]; NUMBER_LETTERS_IN_ALPHABET];
+        for (offset, table) in result.iter_mut().enumerate() {
+            for (input, output) in table.iter_mut().enumerate() {
+                *output = wiring[(input + offset) % NUMBER_LETTERS_IN_ALPHABET];
+            }
         }
         result
     }
 
-    fn generate_reverse_map(vec: &[char]) -> HashMap<char, char> {
-        let mut result = HashMap::with_capacity(NUMBER_LETTERS_IN_ALPHABET);
-        for (i, output) in vec.iter().enumerate() {
-            result.insert(*output, *ALPHABET.get(i).unwrap());
+    fn generate_reverse_tables(wiring: &Table) -> [Table; NUMBER_LETTERS_IN_ALPHABET] {
+        let mut result = [[0; NUMBER_LETTERS_IN_ALPHABET]; NUMBER_LETTERS_IN_ALPHABET];
+        for (offset, table) in result.iter_mut().enumerate() {
+            for (input, output) in wiring.iter().enumerate() {
+                let shifted =
+                    (input + NUMBER_LETTERS_IN_ALPHABET - offset) % NUMBER_LETTERS_IN_ALPHABET;
+                table[*output as usize] = shifted as u8;
+            }
         }
         result
     }
 
+    pub fn get_turnover_positions(&self) -> &[usize] {
+        &self.turnover_positions
+    }
+
+    pub fn is_turnover_position(&self, position: usize) -> bool {
+        self.turnover_positions.contains(&position)
+    }
+
     pub fn turnover_has_occurred(&self) -> bool {
         self.turnover_has_occurred
     }
@@ -132,7 +155,7 @@ impl Rotor {
 
     pub fn increment_position(&mut self) {
         self.position = (self.position + 1) % NUMBER_LETTERS_IN_ALPHABET;
-        if self.position == self.turnover_position {
+        if self.is_turnover_position(self.position) {
             self.turnover_has_occurred = true;
         }
     }
@@ -142,11 +165,25 @@ impl Rotor {
         self.position = position;
     }
 
+    /// The Ringstellung turns the wiring against the letter ring. Turnover only depends on
+    /// the letter ring, so it is not affected.
+    pub fn set_ring_setting(&mut self, ring_setting: usize) {
+        assert!(ring_setting < NUMBER_LETTERS_IN_ALPHABET);
+        self.ring_setting = ring_setting;
+    }
+
+    /// Position of the wiring, i.e. the position corrected by the ring setting.
+    pub fn offset(&self) -> usize {
+        (self.position + NUMBER_LETTERS_IN_ALPHABET - self.ring_setting)
+            % NUMBER_LETTERS_IN_ALPHABET
+    }
+
+    #[allow(dead_code)] // used in tests
     fn shift_char_by_position(&self, input: char, direction: ShiftDirection) -> char {
         let mut ascii = input as usize - ASCII_LETTER_A;
         match direction {
-            ShiftDirection::Forward => ascii += self.position,
-            ShiftDirection::Reverse => ascii -= self.position,
+            ShiftDirection::Forward => ascii += self.offset(),
+            ShiftDirection::Reverse => ascii += NUMBER_LETTERS_IN_ALPHABET - self.offset(),
         }
         ascii = ascii % NUMBER_LETTERS_IN_ALPHABET + ASCII_LETTER_A;
         ascii as u8 as char
@@ -158,24 +195,25 @@ impl Rotor {
 
     pub fn from_file(path: &Path, id: &str) -> Rotor {
         let items = get_items_from_file_for_id(path, id);
-        let mapping = items.0;
-        let turnover_char = items.1;
-        Rotor::new(&mapping, turnover_char)
+        let (mapping, turnover_chars) = items;
+        Rotor::new(&mapping, &turnover_chars)
     }
 
+    pub fn encode_at(&self, offset: usize, input: u8) -> u8 {
+        self.forward[offset][input as usize]
+    }
+
+    pub fn encode_reverse_at(&self, offset: usize, input: u8) -> u8 {
+        self.reverse[offset][input as usize]
+    }
+
+    #[allow(dead_code)] // used in tests
     pub fn encode_char_reverse(&mut self, input: char) -> char {
-        let mut mapping = self.mapping.clone();
-        mapping.rotate_left(self.position);
-        for (position, value) in mapping.iter().enumerate() {
-            if input == *value {
-                return (position + ASCII_LETTER_A) as u8 as char;
-            }
-        }
-        panic!();
+        from_index(self.encode_reverse_at(self.offset(), to_index(input)))
     }
 }
 
-fn get_items_from_file_for_id<'a>(path: &'a Path, id: &'a str) -> (String, char) {
+fn get_items_from_file_for_id<'a>(path: &'a Path, id: &'a str) -> (String, String) {
     let content = fs::read_to_string(path).unwrap();
     let line = find_line_for_rotor_id(&content, id);
     extract_data_from_line(line)
@@ -194,26 +232,22 @@ fn find_line_for_rotor_id<'a>(content: &'a str, id: &'a str) -> Split<'a, char>
     panic!("rotor not found");
 }
 
-fn extract_data_from_line(mut items: Split<char>) -> (String, char) {
+fn extract_data_from_line(mut items: Split<char>) -> (String, String) {
     let pattern = items.next().expect("Rotor pattern missing").to_owned();
-    let turnover_char = items
-        .next()
-        .expect("`:` separator missing")
-        .chars()
-        .next()
-        .unwrap_or_else(|| {
-            panic!(
-                "{} '{}' {}",
-                "Turnover char not found. Consider using", PLACEHOLDER, "as placeholder."
-            )
-        });
-    (pattern, turnover_char)
+    let turnover_chars = items.next().expect("`:` separator missing").to_owned();
+    if turnover_chars.is_empty() {
+        panic!(
+            "{} '{}' {}",
+            "Turnover char not found. Consider using", PLACEHOLDER, "as placeholder."
+        );
+    }
+    (pattern, turnover_chars)
 }
 
-fn mapping_to_vector(mapping: &str) -> Vec<char> {
+fn mapping_to_array(mapping: &str) -> [char; NUMBER_LETTERS_IN_ALPHABET] {
     let vec: Vec<char> = mapping.chars().collect();
     assert_eq!(vec.len(), NUMBER_LETTERS_IN_ALPHABET);
-    vec
+    vec.try_into().unwrap()
 }
 
 #[cfg(test)]
@@ -222,11 +256,12 @@ mod tests {
 
     #[test]
     fn are_mappings_valid() {
-        let vec = vec!["I", "II", "III"];
+        let vec = vec!["I", "II", "III", "IV", "V", "VI", "VII", "VIII"];
         for id in vec {
             Rotor::from_file(get_rotor_path(), id);
         }
         Reflector::from_file(get_rotor_path(), "B");
+        Reflector::from_file(get_rotor_path(), "C");
     }
 
     fn get_rotor_path() -> &'static Path {
@@ -262,7 +297,7 @@ mod tests {
                 new_position
             );
 
-            if new_position == rotor.turnover_position {
+            if rotor.is_turnover_position(new_position) {
                 assert!(rotor.turnover_has_occurred);
                 rotor.reset_turnover_state();
             } else {
@@ -282,6 +317,19 @@ mod tests {
         assert!(rotor.turnover_has_occurred());
     }
 
+    #[test]
+    fn two_notches() {
+        let mut rotor = Rotor::from_file(get_rotor_path(), "VI");
+        assert_eq!(rotor.get_turnover_positions(), &[0, 13]);
+        rotor.set_position(12); // letter M
+        rotor.increment_position(); // letter N
+        assert!(rotor.turnover_has_occurred());
+        rotor.reset_turnover_state();
+        rotor.set_position(25); // letter Z
+        rotor.increment_position(); // letter A
+        assert!(rotor.turnover_has_occurred());
+    }
+
     #[test]
     fn reflector_is_reversible() {
         for input in 'A'..='Z' {
@@ -309,6 +357,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ring_setting_shifts_wiring_but_not_turnover() {
+        let mut rotor = get_cypher_rotor_instance();
+        rotor.set_position(5);
+        let expected = rotor.encode_char('C');
+
+        rotor.set_position(7);
+        rotor.set_ring_setting(2);
+        assert_eq!(rotor.encode_char('C'), expected);
+
+        rotor.set_position(16); // letter Q
+        rotor.increment_position(); // letter R
+        assert!(rotor.turnover_has_occurred());
+    }
+
     #[test]
     fn test_shift_char_by_position_forward() {
         let mut rotor = get_cypher_rotor_instance();
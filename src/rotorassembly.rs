@@ -1,10 +1,38 @@
-use crate::rotor::{Encode, Reflector, Rotor, PATH};
+use crate::alphabet::{
+    from_index, get_position_in_alphabet, is_capital_letter, to_index, NUMBER_LETTERS_IN_ALPHABET,
+};
+use crate::rotor::{Encode, Reflector, Rotor, Table, PATH};
 use std::iter::zip;
 use std::path::Path;
+use std::sync::Arc;
 
+pub const CYPHER_ROTOR_IDS: [&str; 5] = ["I", "II", "III", "IV", "V"];
+pub const NAVAL_ROTOR_IDS: [&str; 8] = ["I", "II", "III", "IV", "V", "VI", "VII", "VIII"];
+pub const REFLECTOR_IDS: [&str; 2] = ["B", "C"];
+
+/// Rotor ids in the order they are inserted into the assembly, fast rotor first.
+pub type WheelOrder = [&'static str; 3];
+
+/// Which rotors and reflectors a machine could be fitted with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MachineModel {
+    /// Army and air force machine with rotors I to V.
+    EnigmaI,
+    /// Navy machine with the additional rotors VI to VIII.
+    M3,
+}
+
+#[derive(Clone)]
 pub struct RotorAssembly {
     rotors: Vec<Rotor>,
     reflector: Reflector,
+    scrambler: Option<Arc<ScramblerTable>>,
+}
+
+/// The permutation of the whole scrambler, i.e. rotors and reflector, for every combination of
+/// wiring offsets. It only depends on the wiring, so it is shared by all clones of an assembly.
+pub struct ScramblerTable {
+    tables: Vec<Table>,
 }
 
 impl RotorAssembly {
@@ -14,53 +42,78 @@ impl RotorAssembly {
         }
     }
 
+    pub fn set_ring_settings(&mut self, ring_settings: [usize; 3]) {
+        for (rotor, ring_setting) in zip(&mut self.rotors, ring_settings) {
+            rotor.set_ring_setting(ring_setting);
+        }
+    }
+
     pub fn new(rotors: Vec<Rotor>, reflector: Reflector) -> Self {
-        RotorAssembly { rotors, reflector }
+        RotorAssembly {
+            rotors,
+            reflector,
+            scrambler: None,
+        }
     }
 
     // used in tests
     #[allow(dead_code)]
     pub fn new_default() -> RotorAssembly {
-        let mut rotors = Vec::new();
-        let ids = vec!["I", "II", "III"];
-        let path = Path::new(PATH);
-        for id in ids {
-            rotors.push(Rotor::from_file(path, id));
-        }
-        let reflector = Reflector::from_file(path, "B");
-        RotorAssembly { rotors, reflector }
+        Self::from_file(Path::new(PATH), ["I", "II", "III"], "B")
     }
 
-    // false positive with debug assertions
-    #[allow(clippy::nonminimal_bool)]
-    fn print_dbg_rotor_state(&self) {
-        if !(cfg!(debug_assertions)) {
-            return;
-        }
-        for i in 0..3 {
-            let rotor = self.rotors.get(i).unwrap();
-            println!("{:#?}", rotor);
+    pub fn from_file(path: &Path, wheel_order: WheelOrder, reflector_id: &str) -> RotorAssembly {
+        let rotors = wheel_order
+            .iter()
+            .map(|id| Rotor::from_file(path, id))
+            .collect();
+        let reflector = Reflector::from_file(path, reflector_id);
+        Self::new(rotors, reflector)
+    }
+
+    /// Replaces the rotor by rotor encoding with a single lookup for every letter. Takes 26^4
+    /// encodings, so it pays off after a few thousand messages.
+    pub fn precompute_scrambler(&mut self) {
+        if self.scrambler.is_none() {
+            self.scrambler = Some(Arc::new(ScramblerTable::new(self)));
         }
-        println!();
     }
 
     pub fn encode_char(&mut self, input: char) -> char {
+        from_index(self.encode_index(to_index(input)))
+    }
+
+    pub fn encode_index(&mut self, input: u8) -> u8 {
         self.increment_cypher_rotor_positions();
-        self.print_dbg_rotor_state();
-        let mut output = self.encode_forward(input);
-        output = self.reflector.encode_char(output);
-        self.encode_reverse(output)
+        self.encode_index_without_stepping(input)
+    }
+
+    /// Encodes `input` at the current rotor positions, as if the key was held down.
+    pub fn encode_index_without_stepping(&self, input: u8) -> u8 {
+        let offsets = self.offsets();
+        match &self.scrambler {
+            Some(scrambler) => scrambler.tables[position_index(offsets)][input as usize],
+            None => self.scramble(offsets, input),
+        }
     }
 
-    fn encode_forward(&mut self, input: char) -> char {
+    fn offsets(&self) -> [usize; 3] {
+        [0, 1, 2].map(|i| self.rotors[i].offset())
+    }
+
+    fn scramble(&self, offsets: [usize; 3], input: u8) -> u8 {
         let mut output = input;
-        for rotor in &mut self.rotors {
-            output = rotor.encode_char(output);
+        for (rotor, offset) in zip(&self.rotors, offsets) {
+            output = rotor.encode_at(offset, output);
+        }
+        output = self.reflector.encode(output);
+        for (rotor, offset) in zip(&self.rotors, offsets).rev() {
+            output = rotor.encode_reverse_at(offset, output);
         }
         output
     }
 
-    fn increment_cypher_rotor_positions(&mut self) {
+    pub fn increment_cypher_rotor_positions(&mut self) {
         self.increment_first_rotor();
         self.carry_over_position_increments();
     }
@@ -81,20 +134,108 @@ impl RotorAssembly {
             }
         }
     }
+}
 
-    fn encode_reverse(&mut self, input: char) -> char {
-        let mut output = input;
-        for rotor in self.rotors.iter_mut().rev() {
-            output = rotor.encode_char_reverse(output);
+impl ScramblerTable {
+    fn new(assembly: &RotorAssembly) -> ScramblerTable {
+        let tables = all_positions()
+            .map(|offsets| {
+                let mut table = [0; NUMBER_LETTERS_IN_ALPHABET];
+                for (input, output) in table.iter_mut().enumerate() {
+                    *output = assembly.scramble(offsets, input as u8);
+                }
+                table
+            })
+            .collect();
+        ScramblerTable { tables }
+    }
+}
+
+impl MachineModel {
+    pub fn parse(name: &str) -> Option<MachineModel> {
+        match name {
+            "enigma-i" => Some(MachineModel::EnigmaI),
+            "m3" => Some(MachineModel::M3),
+            _ => None,
         }
-        output
+    }
+
+    pub fn rotor_ids(self) -> &'static [&'static str] {
+        match self {
+            MachineModel::EnigmaI => &CYPHER_ROTOR_IDS,
+            MachineModel::M3 => &NAVAL_ROTOR_IDS,
+        }
+    }
+
+    pub fn reflector_ids(self) -> &'static [&'static str] {
+        &REFLECTOR_IDS
+    }
+
+    pub fn wheel_orders(self) -> Vec<WheelOrder> {
+        wheel_orders(self.rotor_ids())
     }
 }
 
+/// All orders in which three distinct rotors out of `ids` can be inserted.
+pub fn wheel_orders(ids: &[&'static str]) -> Vec<WheelOrder> {
+    let mut result = Vec::new();
+    for first in ids {
+        for second in ids {
+            for third in ids {
+                if first != second && second != third && first != third {
+                    result.push([*first, *second, *third]);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Parses a wheel order written as `I-II-III`, fast rotor first.
+pub fn parse_wheel_order(value: &str) -> WheelOrder {
+    let ids: Vec<&'static str> = value
+        .split('-')
+        .map(|id| {
+            *NAVAL_ROTOR_IDS
+                .iter()
+                .find(|known| **known == id)
+                .unwrap_or_else(|| panic!("unknown rotor {}", id))
+        })
+        .collect();
+    ids.try_into()
+        .expect("wheel order must consist of three rotors")
+}
+
+/// Parses ring settings written as three letters, fast rotor first, e.g. `AAA`.
+pub fn parse_ring_settings(value: &str) -> [usize; 3] {
+    let ring_settings: Vec<usize> = value
+        .chars()
+        .map(|c| {
+            assert!(is_capital_letter(c), "invalid ring setting {}", c);
+            get_position_in_alphabet(c)
+        })
+        .collect();
+    ring_settings
+        .try_into()
+        .expect("ring settings must consist of three letters")
+}
+
+/// All 26^3 rotor positions, fast rotor first.
+pub fn all_positions() -> impl Iterator<Item = [usize; 3]> {
+    let n = NUMBER_LETTERS_IN_ALPHABET;
+    (0..n * n * n).map(move |i| [i % n, i / n % n, i / (n * n)])
+}
+
+/// Inverse of the enumeration in [`all_positions`].
+pub fn position_index(positions: [usize; 3]) -> usize {
+    let n = NUMBER_LETTERS_IN_ALPHABET;
+    positions[0] + n * positions[1] + n * n * positions[2]
+}
+
 #[cfg(test)]
 mod tests {
     use crate::alphabet::{ALPHABET, NUMBER_LETTERS_IN_ALPHABET};
-    use crate::rotorassembly::RotorAssembly;
+    use crate::rotorassembly::*;
 
     #[test]
     fn can_init() {
@@ -104,9 +245,9 @@ mod tests {
     #[test]
     fn encode_char() {
         let mut assembly = RotorAssembly::new_default();
-        let old_position = assembly.rotors.get(0).unwrap().get_position();
+        let old_position = assembly.rotors.first().unwrap().get_position();
         assert_eq!(assembly.encode_char('A'), 'E');
-        let new_position = assembly.rotors.get(0).unwrap().get_position();
+        let new_position = assembly.rotors.first().unwrap().get_position();
         assert_eq!(
             (old_position + 1) % NUMBER_LETTERS_IN_ALPHABET,
             new_position
@@ -127,4 +268,63 @@ mod tests {
             assert_ne!(input, cypher);
         }
     }
+
+    #[test]
+    fn precomputed_scrambler_encodes_like_rotors() {
+        let mut assembly = RotorAssembly::new_default();
+        assembly.set_ring_settings([3, 0, 7]);
+        let mut precomputed = assembly.clone();
+        precomputed.precompute_scrambler();
+        for input in ALPHABET.iter().cycle().take(1000) {
+            assert_eq!(
+                assembly.encode_char(*input),
+                precomputed.encode_char(*input)
+            );
+        }
+    }
+
+    #[test]
+    fn machine_models() {
+        assert_eq!(MachineModel::parse("enigma-i"), Some(MachineModel::EnigmaI));
+        assert_eq!(MachineModel::EnigmaI.wheel_orders().len(), 60);
+        assert_eq!(MachineModel::parse("m3").unwrap().wheel_orders().len(), 336);
+        assert_eq!(MachineModel::parse("m4"), None);
+    }
+
+    #[test]
+    fn all_wheel_orders() {
+        let wheel_orders = wheel_orders(&CYPHER_ROTOR_IDS);
+        assert_eq!(wheel_orders.len(), 60);
+        assert!(wheel_orders.contains(&["I", "II", "III"]));
+        assert!(!wheel_orders.contains(&["I", "I", "III"]));
+    }
+
+    #[test]
+    fn test_position_index() {
+        for (i, positions) in all_positions().enumerate() {
+            assert_eq!(position_index(positions), i);
+        }
+    }
+
+    #[test]
+    fn test_parse_wheel_order() {
+        assert_eq!(parse_wheel_order("V-I-III"), ["V", "I", "III"]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn parse_wheel_order_with_unknown_rotor() {
+        parse_wheel_order("I-II-XI");
+    }
+
+    #[test]
+    fn test_parse_ring_settings() {
+        assert_eq!(parse_ring_settings("BAZ"), [1, 0, 25]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn parse_ring_settings_with_two_letters() {
+        parse_ring_settings("BA");
+    }
 }
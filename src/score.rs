@@ -0,0 +1,62 @@
+use crate::crib::WILDCARD;
+use std::iter::zip;
+
+/// Rates how much a decrypted text looks like plaintext. Higher is better.
+pub trait Scorer: Send + Sync {
+    fn score(&self, text: &str) -> f64;
+
+    /// Identifies the scorer and its parameters, e.g. to check that a search is resumed with
+    /// the same scorer.
+    fn describe(&self) -> String;
+}
+
+/// Fraction of the letters of a known plaintext that appear at `offset` in the text. Wildcards
+/// of the crib match nothing and do not count.
+pub struct CribScorer {
+    crib: String,
+    offset: usize,
+}
+
+impl CribScorer {
+    pub fn new(crib: String, offset: usize) -> Self {
+        assert!(
+            crib.chars().any(|c| c != WILDCARD),
+            "crib must contain a letter"
+        );
+        CribScorer { crib, offset }
+    }
+}
+
+impl Scorer for CribScorer {
+    fn score(&self, text: &str) -> f64 {
+        let text = text.chars().skip(self.offset);
+        let matches = zip(text, self.crib.chars()).filter(|(a, b)| a == b).count();
+        let letters = self.crib.chars().filter(|c| *c != WILDCARD).count();
+        matches as f64 / letters as f64
+    }
+
+    fn describe(&self) -> String {
+        format!("crib {} {}", self.crib, self.offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::score::*;
+
+    #[test]
+    fn crib_scorer() {
+        let scorer = CribScorer::new("WETTER".to_string(), 2);
+        assert_eq!(scorer.score("XXWETTERXX"), 1.0);
+        assert_eq!(scorer.score("XXWETXXX"), 0.5);
+        assert_eq!(scorer.score("WETTER"), 0.0);
+        assert_eq!(scorer.describe(), "crib WETTER 2");
+    }
+
+    #[test]
+    fn wildcards_do_not_count() {
+        let scorer = CribScorer::new("WE??ER".to_string(), 0);
+        assert_eq!(scorer.score("WETTER"), 1.0);
+        assert_eq!(scorer.score("WEXXXX"), 0.5);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::alphabet::{
+    get_position_in_alphabet, letters_from_positions, NUMBER_LETTERS_IN_ALPHABET,
+};
+use crate::message::{data_lines, Indicator};
+use crate::mode::Mode;
+use crate::parallel::map_in_parallel;
+use crate::rotor::Rotor;
+use crate::rotorassembly::{all_positions, position_index, RotorAssembly, WheelOrder};
+use std::fmt::{Display, Formatter};
+use std::iter::zip;
+use std::path::Path;
+
+const TRIPLET_LENGTH: usize = 3;
+
+/// Indicator of the 1938 procedure: the operator chose the Grundstellung himself, sent it in the
+/// clear and enciphered the doubled message key at it.
+pub struct Intercept {
+    pub grundstellung: [usize; 3],
+    pub indicator: Indicator,
+}
+
+/// A female, i.e. a repeated letter at distance three, observed at `grundstellung`.
+struct Female {
+    grundstellung: [usize; 3],
+    position: usize,
+}
+
+/// Ring settings that are consistent with every usable female of the day.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub wheel_order: WheelOrder,
+    pub ring_settings: [usize; 3],
+}
+
+impl Intercept {
+    /// Parses an intercept written as `QRS ABCABD`.
+    pub fn parse(line: &str) -> Intercept {
+        let (grundstellung, indicator) = line
+            .split_once(' ')
+            .expect("intercept must consist of Grundstellung and indicator");
+        let grundstellung: Vec<usize> = grundstellung
+            .chars()
+            .map(get_position_in_alphabet)
+            .collect();
+        let indicator = Indicator::new(indicator.to_string());
+        indicator.sanity_check(&Mode::Decrypt);
+        Intercept {
+            grundstellung: grundstellung
+                .try_into()
+                .expect("Grundstellung must have three letters"),
+            indicator,
+        }
+    }
+}
+
+impl Display for Candidate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} ring {}",
+            self.wheel_order.join("-"),
+            letters_from_positions(&self.ring_settings)
+        ))
+    }
+}
+
+/// Reads one intercept per line, see [`data_lines`].
+pub fn read_intercepts(path: &Path) -> Vec<Intercept> {
+    data_lines(path)
+        .iter()
+        .map(|line| Intercept::parse(line))
+        .collect()
+}
+
+/// Lays the perforated sheets of every wheel order over the females of the day and returns the
+/// ring settings at which light shines through all of them.
+///
+/// Like the paper sheets, the simulation assumes that the middle rotor does not move while the
+/// indicator is enciphered. Females for which the fast rotor passes its turnover are ignored.
+pub fn zygalski_attack(
+    path: &Path,
+    intercepts: &[Intercept],
+    wheel_orders: &[WheelOrder],
+    reflector_id: &str,
+) -> Vec<Candidate> {
+    let females: Vec<Female> = intercepts
+        .iter()
+        .flat_map(|intercept| {
+            intercept
+                .indicator
+                .females()
+                .into_iter()
+                .map(|position| Female {
+                    grundstellung: intercept.grundstellung,
+                    position,
+                })
+        })
+        .collect();
+    let candidates = map_in_parallel(wheel_orders, |wheel_order| {
+        let fast_rotor = Rotor::from_file(path, wheel_order[0]);
+        let usable: Vec<&Female> = females
+            .iter()
+            .filter(|female| !passes_turnover(&fast_rotor, female.grundstellung[0]))
+            .collect();
+        let mut assembly = RotorAssembly::from_file(path, *wheel_order, reflector_id);
+        assembly.precompute_scrambler();
+        let sheets = perforate_sheets(&mut assembly);
+        all_positions()
+            .filter(|ring_settings| {
+                usable.iter().all(|female| {
+                    let offsets = subtract(female.grundstellung, *ring_settings);
+                    sheets[position_index(offsets)][female.position]
+                })
+            })
+            .map(|ring_settings| Candidate {
+                wheel_order: *wheel_order,
+                ring_settings,
+            })
+            .collect::<Vec<_>>()
+    });
+    candidates.into_iter().flatten().collect()
+}
+
+/// Number of females in `intercepts`.
+pub fn count_females(intercepts: &[Intercept]) -> usize {
+    intercepts
+        .iter()
+        .map(|intercept| intercept.indicator.females().len())
+        .sum()
+}
+
+fn passes_turnover(fast_rotor: &Rotor, position: usize) -> bool {
+    (1..=2 * TRIPLET_LENGTH)
+        .any(|step| fast_rotor.is_turnover_position((position + step) % NUMBER_LETTERS_IN_ALPHABET))
+}
+
+fn subtract(positions: [usize; 3], ring_settings: [usize; 3]) -> [usize; 3] {
+    let mut result = [0; 3];
+    for (i, offset) in result.iter_mut().enumerate() {
+        *offset = (positions[i] + NUMBER_LETTERS_IN_ALPHABET - ring_settings[i])
+            % NUMBER_LETTERS_IN_ALPHABET;
+    }
+    result
+}
+
+/// For each wiring offset and each of the three letter positions, whether a female can occur
+/// there, i.e. whether the scramblers one and four steps further map some letter to the same
+/// letter. Indexed like [`all_positions`].
+fn perforate_sheets(assembly: &mut RotorAssembly) -> Vec<[bool; TRIPLET_LENGTH]> {
+    all_positions()
+        .map(|offsets| {
+            let mut scramblers = [[0; NUMBER_LETTERS_IN_ALPHABET]; 2 * TRIPLET_LENGTH];
+            for (step, scrambler) in scramblers.iter_mut().enumerate() {
+                let fast = (offsets[0] + step + 1) % NUMBER_LETTERS_IN_ALPHABET;
+                assembly.set_positions([fast, offsets[1], offsets[2]]);
+                for (input, output) in scrambler.iter_mut().enumerate() {
+                    *output = assembly.encode_index_without_stepping(input as u8);
+                }
+            }
+            [0, 1, 2]
+                .map(|i| zip(scramblers[i], scramblers[i + TRIPLET_LENGTH]).any(|(a, b)| a == b))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::Message;
+    use crate::random::Random;
+    use crate::rotor::PATH;
+    use crate::zygalski::*;
+    use crate::{plugboard, Enigma, Plugboard};
+
+    const RING_SETTINGS: [usize; 3] = [4, 19, 11];
+
+    fn intercepts_of_a_day() -> Vec<Intercept> {
+        let plugboard = Plugboard::from_file(Path::new(plugboard::PATH));
+        let mut enigma = Enigma::new(RotorAssembly::new_default(), plugboard);
+        enigma.set_ring_settings(RING_SETTINGS);
+        let mut random = Random::new(1938);
+        let mut next_letter = || random.below(NUMBER_LETTERS_IN_ALPHABET);
+        let mut result = Vec::new();
+        for _ in 0..300 {
+            let grundstellung = [next_letter(), next_letter(), next_letter()];
+            let key = letters_from_positions(&[next_letter(), next_letter(), next_letter()]);
+            enigma.set_positions(grundstellung);
+            let message = enigma.encrypt(Message::new(Indicator::new(key), String::new()));
+            result.push(Intercept {
+                grundstellung,
+                indicator: message.indicator,
+            });
+        }
+        result
+    }
+
+    #[test]
+    fn parse_intercept() {
+        let intercept = Intercept::parse("QRS ABCABD");
+        assert_eq!(intercept.grundstellung, [16, 17, 18]);
+        assert_eq!(intercept.indicator.females(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_subtract() {
+        assert_eq!(subtract([0, 5, 25], [1, 5, 0]), [25, 0, 25]);
+    }
+
+    #[test]
+    fn finds_ring_settings() {
+        let intercepts = intercepts_of_a_day();
+        assert!(count_females(&intercepts) > 10);
+        let candidates = zygalski_attack(Path::new(PATH), &intercepts, &[["I", "II", "III"]], "B");
+        let expected = Candidate {
+            wheel_order: ["I", "II", "III"],
+            ring_settings: RING_SETTINGS,
+        };
+        assert!(candidates.contains(&expected));
+        assert!(candidates.len() < 100);
+    }
+}